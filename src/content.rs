@@ -0,0 +1,239 @@
+// Helpers for the content JSON stored by proma in pr_post.content and pr_comment.content
+// The content is an array of blocks, e.g. [{"type": "text", "content": "..."}, {"type": "url", "content": {...}}]
+
+fn get_blocks(content: &serde_json::Value) -> &[serde_json::Value] {
+    match content.as_array() {
+        Some(blocks) => blocks,
+        None => &[],
+    }
+}
+
+fn get_block_type(block: &serde_json::Value) -> &str {
+    block["type"].as_str().unwrap_or_default()
+}
+
+fn find_bare_urls(text: &str) -> Vec<String> {
+    let mut urls = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("http") {
+        let candidate = &rest[start..];
+        if candidate.starts_with("http://") || candidate.starts_with("https://") {
+            let end = candidate
+                .find(|c: char| {
+                    c.is_whitespace() || !c.is_ascii() || c == '"' || c == '<' || c == '>'
+                })
+                .unwrap_or(candidate.len());
+            urls.push(candidate[..end].to_string());
+            rest = &candidate[end..];
+        } else {
+            rest = &candidate[4..];
+        }
+    }
+    urls
+}
+
+fn percent_decode(encoded: &str) -> String {
+    let bytes = encoded.as_bytes();
+    let mut decoded: Vec<u8> = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).unwrap_or_default();
+            if let Ok(byte) = u8::from_str_radix(hex, 16) {
+                decoded.push(byte);
+                i += 3;
+                continue;
+            }
+        }
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+// tieba wraps outbound links with its own redirector, unwrap it to get the real destination
+fn unwrap_redirector(url: &str) -> String {
+    match url.split_once("/mo/q/checkurl?") {
+        Some((prefix, query)) if prefix.ends_with("tieba.baidu.com") => {
+            match query.split('&').find_map(|pair| pair.strip_prefix("url=")) {
+                Some(target) => percent_decode(target),
+                None => url.to_string(),
+            }
+        }
+        _ => url.to_string(),
+    }
+}
+
+pub fn get_domain(url: &str) -> String {
+    let without_scheme = match url.split_once("://") {
+        Some((_, rest)) => rest,
+        None => url,
+    };
+    let authority = without_scheme
+        .split(['/', '?', '#'])
+        .next()
+        .unwrap_or_default();
+    let host = match authority.rsplit_once('@') {
+        Some((_, host)) => host,
+        None => authority,
+    };
+    let host = match host.rsplit_once(':') {
+        Some((host, port)) if port.chars().all(|c| c.is_ascii_digit()) => host,
+        _ => host,
+    };
+    host.to_lowercase()
+}
+
+pub fn extract_links(content: &serde_json::Value) -> Vec<String> {
+    let mut links = Vec::new();
+    for block in get_blocks(content) {
+        match get_block_type(block) {
+            "url" => {
+                let url = match &block["content"] {
+                    serde_json::Value::String(url) => Some(url.as_str()),
+                    other => other["url"].as_str(),
+                };
+                if let Some(url) = url {
+                    links.push(unwrap_redirector(url));
+                }
+            }
+            "text" => {
+                if let Some(text) = block["content"].as_str() {
+                    links.extend(find_bare_urls(text));
+                }
+            }
+            _ => (),
+        }
+    }
+    links
+}
+
+pub struct MentionedName {
+    pub name: String,
+    pub user_id: Option<i64>,
+}
+
+fn get_mention(block: &serde_json::Value) -> Option<MentionedName> {
+    if get_block_type(block) != "username" {
+        return None;
+    }
+    let (text, user_id) = match &block["content"] {
+        serde_json::Value::String(text) => (Some(text.as_str()), None),
+        other => (
+            other["text"]
+                .as_str()
+                .or_else(|| other["username"].as_str()),
+            other["user_id"].as_i64(),
+        ),
+    };
+    let name = text?.trim().trim_start_matches('@').trim();
+    match name.is_empty() {
+        true => None,
+        false => Some(MentionedName {
+            name: name.to_string(),
+            user_id,
+        }),
+    }
+}
+
+pub fn extract_mentions(content: &serde_json::Value) -> Vec<MentionedName> {
+    get_blocks(content).iter().filter_map(get_mention).collect()
+}
+
+// comments replying to another commenter start with "回复 xxx :", where xxx is either plain text or a username block
+pub fn extract_reply_to(content: &serde_json::Value) -> Option<MentionedName> {
+    let blocks = get_blocks(content);
+    let first = blocks.first()?;
+    if get_block_type(first) != "text" {
+        return None;
+    }
+    let rest = first["content"]
+        .as_str()?
+        .trim_start()
+        .strip_prefix("回复")?;
+    if rest.trim().is_empty() {
+        return get_mention(blocks.get(1)?);
+    }
+    let (name, _) = rest.split_once([':', '：'])?;
+    let name = name.trim().trim_start_matches('@').trim();
+    match name.is_empty() {
+        true => None,
+        false => Some(MentionedName {
+            name: name.to_string(),
+            user_id: None,
+        }),
+    }
+}
+
+pub struct UsedEmoticon {
+    pub code: String,
+    pub description: Option<String>,
+}
+
+fn get_emoticon(block: &serde_json::Value) -> Option<UsedEmoticon> {
+    if get_block_type(block) != "emoticon" {
+        return None;
+    }
+    match &block["content"] {
+        serde_json::Value::String(code) => Some(UsedEmoticon {
+            code: code.clone(),
+            description: None,
+        }),
+        other => Some(UsedEmoticon {
+            code: other["id"].as_str()?.to_string(),
+            description: other["description"].as_str().map(|d| d.to_string()),
+        }),
+    }
+}
+
+pub fn extract_emoticons(content: &serde_json::Value) -> Vec<UsedEmoticon> {
+    get_blocks(content)
+        .iter()
+        .filter_map(get_emoticon)
+        .collect()
+}
+
+pub fn to_plain_text(content: &serde_json::Value) -> String {
+    let mut text = String::new();
+    for block in get_blocks(content) {
+        match get_block_type(block) {
+            "emoticon" => {
+                if let Some(emoticon) = get_emoticon(block) {
+                    match crate::emoticon::find(&emoticon.code) {
+                        Some(known) => text.push_str(&known.fallback()),
+                        None => text.push_str(&format!(
+                            "[{}]",
+                            emoticon.description.unwrap_or(emoticon.code)
+                        )),
+                    }
+                }
+            }
+            "url" => match &block["content"] {
+                serde_json::Value::String(url) => text.push_str(url),
+                other => text.push_str(
+                    other["text"]
+                        .as_str()
+                        .or_else(|| other["url"].as_str())
+                        .unwrap_or_default(),
+                ),
+            },
+            "username" => {
+                if let Some(mention) = get_mention(block) {
+                    text.push_str(&format!("@{}", mention.name));
+                }
+            }
+            "image" => text.push_str("[图片]"),
+            "video" => text.push_str("[视频]"),
+            "audio" => text.push_str("[语音]"),
+            _ => text.push_str(block["content"].as_str().unwrap_or_default()),
+        }
+    }
+    text
+}
+
+pub fn count_images(content: &serde_json::Value) -> usize {
+    get_blocks(content)
+        .iter()
+        .filter(|block| get_block_type(block) == "image")
+        .count()
+}
@@ -0,0 +1,95 @@
+// Built-in catalog of the classic tieba emoticons, image_emoticon1 to image_emoticon50
+// Images are expected at emoticon/<code>.png on the frontend side
+
+pub struct Emoticon {
+    pub code: &'static str,
+    pub name: &'static str,
+    pub unicode: Option<&'static str>,
+}
+
+impl Emoticon {
+    pub fn asset(&self) -> String {
+        format!("emoticon/{}.png", self.code)
+    }
+
+    pub fn fallback(&self) -> String {
+        match self.unicode {
+            Some(unicode) => unicode.to_string(),
+            None => format!("[{}]", self.name),
+        }
+    }
+}
+
+macro_rules! emoticon {
+    ($code:literal, $name:literal, $unicode:expr) => {
+        Emoticon {
+            code: $code,
+            name: $name,
+            unicode: $unicode,
+        }
+    };
+}
+
+pub static EMOTICONS: [Emoticon; 50] = [
+    emoticon!("image_emoticon1", "呵呵", Some("🙂")),
+    emoticon!("image_emoticon2", "哈哈", Some("😄")),
+    emoticon!("image_emoticon3", "吐舌", Some("😛")),
+    emoticon!("image_emoticon4", "啊", Some("😮")),
+    emoticon!("image_emoticon5", "酷", Some("😎")),
+    emoticon!("image_emoticon6", "怒", Some("😡")),
+    emoticon!("image_emoticon7", "开心", Some("😊")),
+    emoticon!("image_emoticon8", "汗", Some("😓")),
+    emoticon!("image_emoticon9", "泪", Some("😢")),
+    emoticon!("image_emoticon10", "黑线", Some("😑")),
+    emoticon!("image_emoticon11", "鄙视", Some("😒")),
+    emoticon!("image_emoticon12", "不高兴", Some("😞")),
+    emoticon!("image_emoticon13", "真棒", Some("😁")),
+    emoticon!("image_emoticon14", "钱", Some("🤑")),
+    emoticon!("image_emoticon15", "疑问", Some("🤔")),
+    emoticon!("image_emoticon16", "阴险", Some("😏")),
+    emoticon!("image_emoticon17", "吐", Some("🤮")),
+    emoticon!("image_emoticon18", "咦", Some("😯")),
+    emoticon!("image_emoticon19", "委屈", Some("🥺")),
+    emoticon!("image_emoticon20", "花心", Some("😍")),
+    emoticon!("image_emoticon21", "呼~", Some("😌")),
+    emoticon!("image_emoticon22", "笑眼", Some("😆")),
+    emoticon!("image_emoticon23", "冷", Some("🥶")),
+    emoticon!("image_emoticon24", "太开心", Some("😂")),
+    emoticon!("image_emoticon25", "滑稽", None), // nothing in unicode comes close
+    emoticon!("image_emoticon26", "勉强", Some("😅")),
+    emoticon!("image_emoticon27", "狂汗", Some("😰")),
+    emoticon!("image_emoticon28", "乖", Some("😇")),
+    emoticon!("image_emoticon29", "睡觉", Some("😴")),
+    emoticon!("image_emoticon30", "惊哭", Some("😱")),
+    emoticon!("image_emoticon31", "生气", Some("😠")),
+    emoticon!("image_emoticon32", "惊讶", Some("😲")),
+    emoticon!("image_emoticon33", "喷", Some("😤")),
+    emoticon!("image_emoticon34", "爱心", Some("❤️")),
+    emoticon!("image_emoticon35", "心碎", Some("💔")),
+    emoticon!("image_emoticon36", "玫瑰", Some("🌹")),
+    emoticon!("image_emoticon37", "礼物", Some("🎁")),
+    emoticon!("image_emoticon38", "彩虹", Some("🌈")),
+    emoticon!("image_emoticon39", "星星月亮", Some("🌙")),
+    emoticon!("image_emoticon40", "太阳", Some("☀️")),
+    emoticon!("image_emoticon41", "钱币", Some("💰")),
+    emoticon!("image_emoticon42", "灯泡", Some("💡")),
+    emoticon!("image_emoticon43", "茶杯", Some("🍵")),
+    emoticon!("image_emoticon44", "蛋糕", Some("🎂")),
+    emoticon!("image_emoticon45", "音乐", Some("🎵")),
+    emoticon!("image_emoticon46", "haha", None),
+    emoticon!("image_emoticon47", "胜利", Some("✌️")),
+    emoticon!("image_emoticon48", "大拇指", Some("👍")),
+    emoticon!("image_emoticon49", "弱", Some("👎")),
+    emoticon!("image_emoticon50", "OK", Some("👌")),
+];
+
+pub fn find(code: &str) -> Option<&'static Emoticon> {
+    EMOTICONS.iter().find(|e| e.code == code)
+}
+
+// lets "[滑稽]" or "😂" typed into a search box find the emoticon blocks
+pub fn find_by_fallback(keyword: &str) -> Option<&'static Emoticon> {
+    EMOTICONS
+        .iter()
+        .find(|e| e.unicode == Some(keyword) || format!("[{}]", e.name) == keyword)
+}
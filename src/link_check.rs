@@ -0,0 +1,128 @@
+// Offline link checker: `ex_nihilo_vault link-check --status-file <path>` or `ex_nihilo_vault link-check --stand-in <host:port>`
+// Results are written to the vault_link_check table of vault.db, which /link reads to mark dead links
+
+use rocket_sync_db_pools::rusqlite::{self, params};
+use std::collections::BTreeSet;
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::time::Duration;
+
+const STAND_IN_TIMEOUT: Duration = Duration::from_secs(10);
+
+enum Source {
+    StatusFile(String),
+    StandIn(String),
+}
+
+// status file format: one "<status> <url>" per line, 0 means unreachable, lines starting with # are ignored
+fn read_status_file(path: &str) -> Result<Vec<(String, u16)>, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("cannot read {}: {}", path, e))?;
+    let mut statuses = Vec::new();
+    for (line_number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (status, url) = line.split_once(char::is_whitespace).ok_or(format!(
+            "{}:{}: expected \"<status> <url>\"",
+            path,
+            line_number + 1
+        ))?;
+        let status = status.parse::<u16>().map_err(|_| {
+            format!(
+                "{}:{}: invalid status \"{}\"",
+                path,
+                line_number + 1,
+                status
+            )
+        })?;
+        statuses.push((url.trim().to_string(), status));
+    }
+    Ok(statuses)
+}
+
+// the stand-in is a plain http proxy, so the request target is the absolute url
+fn ask_stand_in(stand_in: &str, url: &str) -> u16 {
+    let mut stream = match TcpStream::connect(stand_in) {
+        Ok(stream) => stream,
+        Err(_) => return 0,
+    };
+    stream.set_read_timeout(Some(STAND_IN_TIMEOUT)).ok();
+    stream.set_write_timeout(Some(STAND_IN_TIMEOUT)).ok();
+    let request = format!(
+        "HEAD {} HTTP/1.0\r\nHost: {}\r\nConnection: close\r\n\r\n",
+        url,
+        crate::content::get_domain(url)
+    );
+    if stream.write_all(request.as_bytes()).is_err() {
+        return 0;
+    }
+    let mut response = Vec::new();
+    stream.take(1024).read_to_end(&mut response).ok();
+    // HTTP/1.1 404 Not Found
+    String::from_utf8_lossy(&response)
+        .split_whitespace()
+        .nth(1)
+        .and_then(|status| status.parse::<u16>().ok())
+        .unwrap_or(0)
+}
+
+fn parse_args(args: &[String]) -> Result<Source, String> {
+    match args {
+        [flag, path] if flag == "--status-file" => Ok(Source::StatusFile(path.clone())),
+        [flag, address] if flag == "--stand-in" => Ok(Source::StandIn(address.clone())),
+        _ => Err(
+            "usage: ex_nihilo_vault link-check (--status-file <path> | --stand-in <host:port>)"
+                .to_string(),
+        ),
+    }
+}
+
+fn check(vault_path: &str, source: Source) -> Result<usize, String> {
+    let mut c = rusqlite::Connection::open(vault_path).map_err(|e| e.to_string())?;
+    let statuses = match source {
+        Source::StatusFile(path) => read_status_file(&path)?,
+        Source::StandIn(stand_in) => {
+            let urls = crate::collect_link_citations(&c)
+                .map_err(|e| e.to_string())?
+                .into_iter()
+                .map(|(url, _)| url)
+                .collect::<BTreeSet<String>>();
+            urls.into_iter()
+                .map(|url| {
+                    let status = ask_stand_in(&stand_in, &url);
+                    println!("{} {}", status, url);
+                    (url, status)
+                })
+                .collect()
+        }
+    };
+    let tx = c.transaction().map_err(|e| e.to_string())?;
+    tx.execute(
+        "CREATE TABLE IF NOT EXISTS vault_link_check (url TEXT PRIMARY KEY, status INTEGER NOT NULL, checked_time TEXT NOT NULL)",
+        params![],
+    )
+    .map_err(|e| e.to_string())?;
+    for (url, status) in &statuses {
+        tx.execute(
+            "INSERT OR REPLACE INTO vault_link_check VALUES (?, ?, datetime('now'))",
+            params![url, status],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(statuses.len())
+}
+
+pub fn run(vault_path: &str, args: &[String]) -> i32 {
+    match parse_args(args).and_then(|source| check(vault_path, source)) {
+        Ok(count) => {
+            println!("recorded {} link statuses", count);
+            0
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            1
+        }
+    }
+}
@@ -1,22 +1,60 @@
 #[macro_use]
 extern crate rocket;
+use operation::{Lang, Operation};
 use rocket::fairing::{Fairing, Info, Kind};
 use rocket::http::{Header, Status};
+use rocket::request::{FromRequest, Outcome};
 use rocket::response::content::Html;
 use rocket::serde::{json::Json, Deserialize, Serialize};
 use rocket::{Request, Response};
 use rocket_sync_db_pools::rusqlite::params;
 use rocket_sync_db_pools::{database, rusqlite};
 use serde_json::json;
+use std::collections::BTreeMap;
+use visibility::Visibility;
+
+mod content;
+mod emoticon;
+mod link_check;
+mod media;
+mod operation;
+mod tail;
+mod visibility;
 
 const THREAD_CAPACITY_PER_PAGE: u32 = 50;
 const POST_CAPACITY_PER_PAGE: u32 = 30;
 const COMMENT_CAPACITY_PER_PAGE: u32 = 10;
 const USER_RECORD_CAPACITY_PER_PAGE: u32 = 30;
 const ADMIN_LOG_CAPACITY_PER_PAGE: u32 = 50;
+const LINK_DOMAIN_CAPACITY_PER_PAGE: u32 = 50;
+const PREVIEW_LENGTH: usize = 100;
+const MODERATOR_REPORT_TOP_NUM: usize = 10;
+
+// 2022-02-16 01:XX -> rewinder
+// 2022-02-26 23:XX -> rollwinder
+const SHOWDOWN_WINDOWS: [(&str, &str); 2] = [
+    ("rewinder", "2022-02-16 01:"),
+    ("rollwinder", "2022-02-26 23:"),
+];
 
 struct CustomHeaders;
 
+struct AcceptLanguage(Option<String>);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for AcceptLanguage {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        Outcome::Success(AcceptLanguage(
+            request
+                .headers()
+                .get_one("Accept-Language")
+                .map(|header| header.to_string()),
+        ))
+    }
+}
+
 #[database("vault")]
 struct Vault(rusqlite::Connection);
 
@@ -27,6 +65,46 @@ enum UserType {
     Avatar,
 }
 
+enum ThreadOrder {
+    LastReply,
+    Created,
+    ReplyNum,
+    CommentActivity, // number of comments under all floors
+}
+
+struct ThreadFilter {
+    only_good: bool,
+    min_reply_num: Option<i32>,
+    op_user_id: Option<i64>,
+    created_after: Option<String>,
+    created_before: Option<String>,
+}
+
+enum PostOrder {
+    Floor,
+    FloorDesc,
+    MostDiscussed, // comment_num DESC
+}
+
+#[derive(Clone, Copy)]
+enum CommentOrder {
+    Time,
+    TimeDesc,
+}
+
+#[derive(Clone, Copy)]
+enum AdminLogOrder {
+    Time,
+    TimeDesc,
+}
+
+// how posts and comments deleted before time_machine_datetime are shown
+#[derive(Clone, Copy)]
+enum View {
+    Public,    // hidden
+    Moderator, // shown, with the 删贴 log attached
+}
+
 #[derive(Serialize, Deserialize)]
 struct User {
     user_id: i64,
@@ -62,13 +140,38 @@ enum UserRecord {
     },
 }
 
+#[derive(Serialize, Deserialize)]
+struct MentionRecord {
+    user_id: i64, // who mentioned
+    #[serde(flatten)]
+    record: UserRecord,
+}
+
+#[derive(Serialize, Deserialize)]
+struct MentionEdge {
+    user_id: i64,
+    mentioned_name: String,
+    mentioned_user_id: Option<i64>, // None if the name cannot be resolved against pr_user
+    count: u32,
+}
+
+#[derive(Clone, Copy)]
 enum AdminLogCategory {
     User,
     Post,
     Bawu,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone)]
+struct MediaEntry {
+    media_type: String, // "image" or "video"
+    url: String,
+    width: Option<u32>,
+    height: Option<u32>,
+    mirror_path: Option<String>, // from vault_media_mirror, if there is a local mirror
+}
+
+#[derive(Serialize, Deserialize, Clone)]
 #[serde(untagged)]
 enum AdminLog {
     Post {
@@ -77,6 +180,7 @@ enum AdminLog {
         title: String,
         content_preview: String,
         media: Option<String>,
+        media_entries: Vec<MediaEntry>, // media parsed
         username: String,
         post_time: String,
         operation: String,
@@ -110,13 +214,106 @@ struct ThreadMetadata {
 #[derive(Serialize, Deserialize)]
 struct Thread {
     thread_id: i64,
-    op_user_id: i64, // op: original poster, aka. floor == 1
+    op_user_id: Option<i64>, // op: original poster, aka. floor == 1, none if a reconstructed op is not in pr_user
     title: String,
-    user_id: i64,
+    user_id: Option<i64>,
     time: String,
     reply_num: i32,
     is_good: bool,
     op_post_content: serde_json::Value,
+    created_time: String,                // time of floor 1
+    reconstructed: bool,                 // never scraped, pieced together from un_post
+    op_username: Option<String>,         // only for reconstructed threads
+    last_reply_username: Option<String>, // same as above
+}
+
+#[derive(Serialize, Deserialize)]
+struct FeaturedThread {
+    thread_id: i64,
+    title: String,
+    op_user: User,
+    created_time: String,
+    reply_num: i32,
+    preview: String, // plain text of floor 1, cut at PREVIEW_LENGTH chars
+}
+
+#[derive(Serialize, Deserialize)]
+struct DiscoveredThread {
+    thread_id: i64,
+    title: String,
+    op_user_id: i64,
+    created_time: String,
+    reply_num: i32,
+    is_good: bool,
+    preview: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct OnThisDayThread {
+    year: String,
+    activity_num: u32, // posts and comments on that day of that year
+    created_on_this_day: bool,
+    #[serde(flatten)]
+    thread: DiscoveredThread,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ReplyCount {
+    reply_num: u32, // floors other than floor 1
+    comment_num: u32,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ThreadStatistics {
+    created_time: Option<String>,
+    last_reply_time: Option<String>,
+    post_num: u32,    // archived floors, unlike reply_num
+    comment_num: u32, // archived comments
+    participant_num: u32,
+    image_num: u32,
+    deletion_num: u32, // threads, posts and comments still deleted per un_post, the showdown excluded
+}
+
+// stand-in for a post of a thread missing from pr_thread, pieced together from un_post
+#[derive(Serialize, Deserialize)]
+struct GhostPost {
+    post_id: Option<i64>, // none for the thread-level log, i.e. floor 1
+    user_id: Option<i64>, // if the username can be found in pr_user
+    username: String,
+    title: String,
+    content_preview: String,
+    media: Option<String>,
+    time: String,
+    reconstructed: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+struct VisibilityChange {
+    operation: String,
+    operation_code: String,
+    operation_label: String,
+    operator: String,
+    operation_time: String,
+    visibility: String, // after this operation, "visible" or "deleted"
+}
+
+// the latest un_post operation on a post or a comment, e.g. 删贴 for a floor removed after the scraping
+// the 删贴 log behind a hidden post or comment, with the fields localize_admin_logs adds
+#[derive(Serialize, Deserialize, Clone)]
+struct DeletionLog {
+    #[serde(flatten)]
+    admin_log: AdminLog,
+    operation_code: String,
+    operation_label: String, // zh-CN until localize_moderation
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct Moderation {
+    operation: String,
+    operation_code: String,
+    operation_label: String, // zh-CN until localize_moderation
+    operator: String,
+    operation_time: String,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -129,6 +326,8 @@ struct Post {
     comment_num: i32,
     signature: Option<String>,
     tail: Option<String>,
+    deletion: Option<DeletionLog>, // only in the moderator view of the time machine
+    moderation: Option<Moderation>,
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -137,6 +336,111 @@ struct Comment {
     user_id: i64,
     content: serde_json::Value,
     time: String,
+    reply_to_user_id: Option<i64>, // parsed from the leading "回复 xxx :"
+    reply_to_comment_id: Option<i64>, // only if xxx has exactly one earlier comment under the same post
+    deletion: Option<DeletionLog>, // only in the moderator view of the time machine, the floor's log if the floor is deleted
+    moderation: Option<Moderation>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct LinkCitation {
+    thread_id: i64,
+    post_id: i64,
+    comment_id: Option<i64>,
+    time: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct OutboundLink {
+    url: String,
+    count: u32,
+    first_seen: String,
+    last_seen: String,
+    status: Option<u16>, // from vault_link_check, 0 means unreachable
+    dead: Option<bool>,
+    citations: Vec<LinkCitation>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct LinkDomain {
+    domain: String,
+    count: u32,
+    first_seen: String,
+    last_seen: String,
+    links: Vec<OutboundLink>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct EmoticonUsage {
+    code: String,
+    name: Option<String>,
+    asset: Option<String>, // None if the emoticon is not in the built-in catalog
+    unicode: Option<String>,
+    fallback: String,
+    count: u32,
+}
+
+#[derive(Serialize, Deserialize)]
+struct TailMonth {
+    month: String,
+    total: u32,
+    platforms: BTreeMap<String, u32>,
+    shares: BTreeMap<String, f64>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct TailClient {
+    client: String,
+    platform: String,
+    count: u32,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ActivePeriod {
+    start: String, // YYYY-MM
+    end: String,
+    count: u32,
+}
+
+#[derive(Serialize, Deserialize)]
+struct DayCount {
+    day: String,
+    count: u32,
+}
+
+#[derive(Serialize, Deserialize)]
+struct TargetCount {
+    username: String,
+    count: u32,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ModeratorActivity {
+    operator: String,
+    count: u32,
+    operations: BTreeMap<String, u32>, // by operation code
+    first_seen: String,
+    last_seen: String,
+    active_periods: Vec<ActivePeriod>, // runs of consecutive months with at least one action
+    busiest_days: Vec<DayCount>,
+    most_targeted_users: Vec<TargetCount>,
+    showdown_shares: BTreeMap<String, f64>, // share of the actions inside each showdown window
+}
+
+#[derive(Serialize, Deserialize)]
+struct SignaturePost {
+    thread_id: i64,
+    post_id: i64,
+    floor: i32,
+    time: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SignatureHistory {
+    signature: String,
+    first_seen: String,
+    last_seen: String,
+    posts: Vec<SignaturePost>,
 }
 
 async fn get_thread_metadata(vault: &Vault, thread_id: i64) -> Option<ThreadMetadata> {
@@ -183,6 +487,41 @@ async fn get_user_metadata(vault: &Vault, user_type: UserType, user_clue: String
     Some(user)
 }
 
+async fn get_post_thread_id(vault: &Vault, post_id: i64) -> Option<i64> {
+    vault
+        .run(move |c| {
+            c.query_row(
+                "SELECT thread_id FROM pr_post WHERE id = ?",
+                params![post_id],
+                |r| r.get(0),
+            )
+        })
+        .await
+        .ok()
+}
+
+async fn get_comment_post_id(vault: &Vault, comment_id: i64) -> Option<i64> {
+    vault
+        .run(move |c| {
+            c.query_row(
+                "SELECT post_id FROM pr_comment WHERE id = ?",
+                params![comment_id],
+                |r| r.get(0),
+            )
+        })
+        .await
+        .ok()
+}
+
+// "operation_time NOT LIKE ... AND ..." over every showdown window
+fn get_showdown_exclusion_sql() -> String {
+    SHOWDOWN_WINDOWS
+        .iter()
+        .map(|(_, prefix)| format!("operation_time NOT LIKE '{}%'", prefix))
+        .collect::<Vec<String>>()
+        .join(" AND ")
+}
+
 fn get_datetime_sql_param(datetime: Option<String>) -> String {
     match datetime {
         Some(datetime) => datetime,
@@ -190,6 +529,132 @@ fn get_datetime_sql_param(datetime: Option<String>) -> String {
     }
 }
 
+fn get_media_entries(media: Option<&str>) -> Vec<MediaEntry> {
+    media::parse(media)
+        .into_iter()
+        .map(|media| MediaEntry {
+            media_type: media.kind.code().to_string(),
+            url: media.url,
+            width: media.width,
+            height: media.height,
+            mirror_path: None,
+        })
+        .collect()
+}
+
+// the table only exists if a media mirror has been set up next to vault.db
+fn link_media_mirror<'a>(
+    c: &rusqlite::Connection,
+    admin_logs: impl IntoIterator<Item = &'a mut AdminLog>,
+) -> Result<(), rusqlite::Error> {
+    let mut stmt = match c.prepare("SELECT path FROM vault_media_mirror WHERE url = ?") {
+        Ok(stmt) => stmt,
+        Err(_) => return Ok(()),
+    };
+    for admin_log in admin_logs {
+        if let AdminLog::Post { media_entries, .. } = admin_log {
+            for media_entry in media_entries.iter_mut() {
+                media_entry.mirror_path = stmt
+                    .query_map(params![media_entry.url], |r| r.get::<usize, String>(0))?
+                    .next()
+                    .transpose()?;
+            }
+        }
+    }
+    Ok(())
+}
+
+// the lang parameter wins over Accept-Language, an unknown lang parameter is an error
+fn get_lang(lang: Option<String>, accept_language: AcceptLanguage) -> Option<Lang> {
+    match lang {
+        Some(lang) => Lang::parse(&lang),
+        None => Some(
+            accept_language
+                .0
+                .as_deref()
+                .and_then(Lang::from_accept_language)
+                .unwrap_or(Lang::ZhCn),
+        ),
+    }
+}
+
+fn get_deletion_log(admin_log: AdminLog) -> DeletionLog {
+    let operation = match &admin_log {
+        AdminLog::Post { operation, .. } => Operation::parse(operation),
+        _ => Operation::Other,
+    };
+    DeletionLog {
+        operation_code: operation.code().to_string(),
+        operation_label: operation.label(Lang::ZhCn).to_string(),
+        admin_log,
+    }
+}
+
+// relabels both the moderation and, in the moderator view, the deletion of a post or comment
+fn localize_moderation(
+    moderation: &mut Option<Moderation>,
+    deletion: &mut Option<DeletionLog>,
+    lang: Lang,
+) {
+    if let Some(moderation) = moderation {
+        moderation.operation_label = Operation::parse(&moderation.operation)
+            .label(lang)
+            .to_string();
+    }
+    if let Some(DeletionLog {
+        admin_log: AdminLog::Post { operation, .. },
+        operation_label,
+        ..
+    }) = deletion
+    {
+        *operation_label = Operation::parse(operation).label(lang).to_string();
+    }
+}
+
+// raw logs plus operation_code, operation_label and, for un_user, duration_seconds
+fn localize_admin_logs(admin_logs: &[AdminLog], lang: Lang) -> Vec<serde_json::Value> {
+    admin_logs
+        .iter()
+        .map(|admin_log| {
+            let mut localized = json!(admin_log);
+            let operation = match admin_log {
+                AdminLog::Post { operation, .. } => operation,
+                AdminLog::User { operation, .. } => operation,
+                AdminLog::Bawu { operation, .. } => operation,
+            };
+            let operation = Operation::parse(operation);
+            localized["operation_code"] = json!(operation.code());
+            localized["operation_label"] = json!(operation.label(lang));
+            if let AdminLog::User { duration, .. } = admin_log {
+                localized["duration_seconds"] =
+                    json!(duration.as_deref().and_then(operation::parse_duration));
+            }
+            localized
+        })
+        .collect()
+}
+
+// shared by every search box, the result goes through get_keyword_sql_param
+fn get_search_keyword(search_keyword: Option<String>) -> Option<String> {
+    // XXX: one keyword only?!
+    // blank input gives none, like no search at all
+    search_keyword.and_then(|keyword| keyword.split_whitespace().next().map(str::to_string))
+}
+
+// char offsets of every case-insensitive match, like sqlite LIKE does for ascii
+fn get_highlights(text: &str, keyword: &str) -> Vec<[usize; 2]> {
+    let text_lowercase = text.to_ascii_lowercase();
+    let keyword_lowercase = keyword.to_ascii_lowercase();
+    let keyword_len = keyword.chars().count();
+    text_lowercase
+        .match_indices(keyword_lowercase.as_str())
+        .map(|(start, _)| {
+            let start = text[..start].chars().count();
+            [start, start + keyword_len]
+        })
+        .collect()
+}
+
 fn get_keyword_sql_param(keyword: Option<String>) -> String {
     match keyword {
         Some(keyword) => format!("%{}%", keyword),
@@ -201,9 +666,10 @@ async fn get_threads(
     vault: &Vault,
     time_machine_datetime: Option<String>,
     search_keyword: Option<String>,
+    order: ThreadOrder,
+    filter: ThreadFilter,
 ) -> Result<Vec<Thread>, rusqlite::Error> {
-    let sql = match &time_machine_datetime {
-        None => "SELECT x.thread_id, t.user_id, title, x.user_id, x.time, reply_num, is_good, p.content FROM (
+    let sql = "SELECT x.thread_id, t.user_id, title, x.user_id, x.time, reply_num, is_good, p.content, p.time FROM (
             SELECT * FROM (
                 SELECT * FROM (
                     SELECT thread_id, user_id, time, content
@@ -228,47 +694,15 @@ async fn get_threads(
         ) AS x
         JOIN pr_thread AS t ON x.thread_id = t.id
         JOIN pr_post AS p ON x.thread_id = p.thread_id AND p.floor = 1
-        ORDER BY x.time DESC", // feel the pain: this monster takes ~110 ms to execute and eats a lot of cpu, use proxy_cache to mitigate
-        Some(_) => "SELECT x.thread_id, t.user_id, title, x.user_id, x.time, reply_num, is_good, p.content,operation FROM (
-            SELECT * FROM (
-                SELECT y.*,operation FROM (
-                    SELECT * FROM (
-                        SELECT * FROM (
-                            SELECT thread_id, user_id, time, content
-                            FROM pr_post
-                            WHERE time < ?1 AND content LIKE ?2
-                            ORDER BY time DESC
-                        )
-                        GROUP BY thread_id
-                        UNION
-                        SELECT * FROM (
-                            SELECT thread_id, pr_comment.user_id, pr_comment.time, pr_comment.content
-                            FROM pr_comment
-                            JOIN pr_post ON pr_comment.post_id = pr_post.id
-                            WHERE pr_comment.time < ?1 AND pr_comment.content LIKE ?2
-                            ORDER BY pr_comment.time DESC
-                        )
-                        GROUP BY thread_id
-                        ORDER BY time DESC
-                    )
-                    GROUP BY thread_id
-                    ORDER BY time DESC
-                ) AS y
-                LEFT JOIN un_post AS u ON u.thread_id = y.thread_id AND u.post_id IS NULL AND u.operation LIKE '%删贴' AND operation_time < ?1 AND operation_time NOT LIKE '2022-02-26 23:%' AND operation_time NOT LIKE '2022-02-16 01:%' 
-                GROUP BY y.thread_id
-            )
-            WHERE operation IS NULL OR operation <> '删贴'
-            ORDER BY time DESC
-        ) AS x
-        JOIN pr_thread AS t ON x.thread_id = t.id
-        JOIN pr_post AS p ON x.thread_id = p.thread_id AND p.floor = 1
-        ORDER BY x.time DESC" // this one takes 120 ms, fuck
-    };
-    let datetime = get_datetime_sql_param(time_machine_datetime);
+        ORDER BY x.time DESC"; // feel the pain: this monster takes ~110 ms to execute and eats a lot of cpu, use proxy_cache to mitigate
+
+    let is_time_machine = time_machine_datetime.is_some();
+    let datetime = get_datetime_sql_param(time_machine_datetime.clone());
     let keyword = get_keyword_sql_param(search_keyword);
     let threads = vault
-        .run(move |c| {
-            c.prepare(sql)?
+        .run(move |c| -> Result<Vec<Thread>, rusqlite::Error> {
+            let threads = c
+                .prepare(sql)?
                 .query_map(params![datetime, keyword], |r| {
                     Ok(Thread {
                         thread_id: r.get(0)?,
@@ -280,108 +714,896 @@ async fn get_threads(
                         is_good: r.get(6)?,
                         op_post_content: serde_json::from_str(r.get::<usize, String>(7)?.as_str())
                             .unwrap(),
+                        created_time: r.get(8)?,
+                        reconstructed: false,
+                        op_username: None,
+                        last_reply_username: None,
                     })
                 })?
-                .collect::<Result<Vec<Thread>, _>>()
+                .collect::<Result<Vec<Thread>, _>>()?;
+            if !is_time_machine {
+                return Ok(threads);
+            }
+            let deleted_threads = get_deleted_threads(c, &datetime)?;
+            let mut threads = threads
+                .into_iter()
+                .chain(get_ghost_threads(c, &datetime, &keyword)?)
+                .filter(|thread| !deleted_threads.contains_key(&thread.thread_id))
+                .collect::<Vec<Thread>>();
+            threads.sort_by(|a, b| b.time.cmp(&a.time));
+            Ok(threads)
         })
         .await?;
+
+    let mut threads = threads
+        .into_iter()
+        .filter(|thread| !filter.only_good || thread.is_good)
+        .filter(|thread| match filter.min_reply_num {
+            Some(min_reply_num) => thread.reply_num >= min_reply_num,
+            None => true,
+        })
+        .filter(|thread| match filter.op_user_id {
+            Some(op_user_id) => thread.op_user_id == Some(op_user_id),
+            None => true,
+        })
+        .filter(|thread| match &filter.created_after {
+            Some(created_after) => &thread.created_time >= created_after,
+            None => true,
+        })
+        .filter(|thread| match &filter.created_before {
+            Some(created_before) => &thread.created_time < created_before,
+            None => true,
+        })
+        .collect::<Vec<Thread>>();
+
+    // the sql already orders by last reply
+    match order {
+        ThreadOrder::LastReply => (),
+        ThreadOrder::Created => {
+            threads.sort_by(|a, b| b.created_time.cmp(&a.created_time));
+        }
+        ThreadOrder::ReplyNum => {
+            threads.sort_by_key(|thread| std::cmp::Reverse(thread.reply_num));
+        }
+        ThreadOrder::CommentActivity => {
+            let datetime = get_datetime_sql_param(time_machine_datetime);
+            let comment_nums = vault
+                .run(move |c| {
+                    c.prepare(
+                        "SELECT thread_id, COUNT(*)
+                             FROM pr_comment
+                             JOIN pr_post
+                             ON pr_comment.post_id = pr_post.id
+                             WHERE pr_comment.time < ?
+                             GROUP BY thread_id",
+                    )?
+                    .query_map(params![datetime], |r| {
+                        Ok((r.get::<usize, i64>(0)?, r.get::<usize, u32>(1)?))
+                    })?
+                    .collect::<Result<BTreeMap<i64, u32>, _>>()
+                })
+                .await?;
+            threads.sort_by_key(|thread| {
+                std::cmp::Reverse(comment_nums.get(&thread.thread_id).copied().unwrap_or(0))
+            });
+        }
+    }
     Ok(threads)
 }
 
-async fn get_posts(
-    vault: &Vault,
-    thread_id: i64,
-    time_machine_datetime: Option<String>,
-) -> Result<Vec<Post>, rusqlite::Error> {
-    let datetime = get_datetime_sql_param(time_machine_datetime);
-    let posts = vault
-        .run(move |c| {
-            c.prepare("SELECT * FROM pr_post WHERE thread_id = ? AND time < ? ORDER BY floor")?
-                .query_map(params![thread_id, datetime], |r| {
-                    Ok(Post {
-                        post_id: r.get(0)?,
-                        floor: r.get(1)?,
+fn get_preview(content: &serde_json::Value) -> String {
+    content::to_plain_text(content)
+        .chars()
+        .take(PREVIEW_LENGTH)
+        .collect()
+}
+
+async fn get_featured_threads(vault: &Vault) -> Result<Vec<FeaturedThread>, rusqlite::Error> {
+    let threads = vault
+        .run(|c| {
+            c.prepare(
+                "SELECT t.id, t.title, t.user_id, username, nickname, avatar, p.time, t.reply_num, p.content
+                     FROM pr_thread AS t
+                     JOIN pr_post AS p ON t.id = p.thread_id AND p.floor = 1
+                     JOIN pr_user AS u ON t.user_id = u.id
+                     WHERE t.is_good = 1
+                     ORDER BY p.time DESC",
+            )?
+            .query_map(params![], |r| {
+                Ok(FeaturedThread {
+                    thread_id: r.get(0)?,
+                    title: r.get(1)?,
+                    op_user: User {
                         user_id: r.get(2)?,
-                        content: serde_json::from_str(r.get::<usize, String>(3)?.as_str()).unwrap(), // so ugly
-                        time: r.get(4)?,
-                        comment_num: r.get(5)?,
-                        signature: r.get(6)?,
-                        tail: r.get(7)?,
-                    })
-                })?
-                .collect::<Result<Vec<Post>, _>>()
+                        username: r.get(3)?,
+                        nickname: r.get(4)?,
+                        avatar: r.get(5)?,
+                    },
+                    created_time: r.get(6)?,
+                    reply_num: r.get(7)?,
+                    preview: get_preview(
+                        &serde_json::from_str(r.get::<usize, String>(8)?.as_str()).unwrap(),
+                    ),
+                })
+            })?
+            .collect::<Result<Vec<FeaturedThread>, _>>()
         })
         .await?;
-    Ok(posts)
+    Ok(threads)
 }
 
-async fn get_comments(
+async fn get_on_this_day_threads(
     vault: &Vault,
-    post_id: i64,
-    time_machine_datetime: Option<String>,
-) -> Result<Vec<Comment>, rusqlite::Error> {
-    let datetime = get_datetime_sql_param(time_machine_datetime);
-    let comments = vault
-        .run(move |c| {
-            c.prepare("SELECT * FROM pr_comment WHERE post_id = ? AND time < ? ORDER BY time")?
-                .query_map(params![post_id, datetime], |r| {
-                    Ok(Comment {
-                        comment_id: r.get(0)?,
-                        user_id: r.get(1)?,
-                        content: serde_json::from_str(r.get::<usize, String>(2)?.as_str()).unwrap(),
-                        time: r.get(3)?,
+    date: String,
+) -> Result<Vec<OnThisDayThread>, rusqlite::Error> {
+    let sql = "SELECT a.thread_id, t.title, t.user_id, p.time, t.reply_num, t.is_good, p.content, a.year, COUNT(*) AS activity_num
+             FROM (
+                 SELECT thread_id, substr(time, 1, 4) AS year
+                 FROM pr_post
+                 WHERE substr(time, 6, 5) = ?1
+                 UNION ALL
+                 SELECT pr_post.thread_id, substr(pr_comment.time, 1, 4)
+                 FROM pr_comment
+                 JOIN pr_post ON pr_comment.post_id = pr_post.id
+                 WHERE substr(pr_comment.time, 6, 5) = ?1
+             ) AS a
+             JOIN pr_thread AS t ON a.thread_id = t.id
+             JOIN pr_post AS p ON a.thread_id = p.thread_id AND p.floor = 1
+             GROUP BY a.thread_id, a.year
+             ORDER BY a.year DESC, activity_num DESC";
+    let threads = vault
+        .run(move |c| -> Result<Vec<OnThisDayThread>, rusqlite::Error> {
+            let deleted_threads = get_deleted_threads(c, &get_datetime_sql_param(None))?;
+            let threads = c
+                .prepare(sql)?
+                .query_map(params![date], |r| {
+                    let created_time: String = r.get(3)?;
+                    let year: String = r.get(7)?;
+                    Ok(OnThisDayThread {
+                        created_on_this_day: created_time.starts_with(&year)
+                            && created_time.get(5..10) == Some(date.as_str()),
+                        year,
+                        activity_num: r.get(8)?,
+                        thread: DiscoveredThread {
+                            thread_id: r.get(0)?,
+                            title: r.get(1)?,
+                            op_user_id: r.get(2)?,
+                            created_time,
+                            reply_num: r.get(4)?,
+                            is_good: r.get(5)?,
+                            preview: get_preview(
+                                &serde_json::from_str(r.get::<usize, String>(6)?.as_str()).unwrap(),
+                            ),
+                        },
                     })
                 })?
-                .collect::<Result<Vec<Comment>, _>>()
+                .collect::<Result<Vec<OnThisDayThread>, _>>()?;
+            Ok(threads
+                .into_iter()
+                .filter(|thread| !deleted_threads.contains_key(&thread.thread.thread_id))
+                .collect())
         })
         .await?;
-    Ok(comments)
+    Ok(threads)
 }
 
-async fn get_post_related_admin_logs(
+async fn get_random_thread(
     vault: &Vault,
-    thread_id: Option<i64>,
-    post_id: Option<i64>,
-    time_machine_datetime: Option<String>,
-) -> Result<Vec<AdminLog>, rusqlite::Error> {
-    let datetime = get_datetime_sql_param(time_machine_datetime);
-    let sql = match thread_id {
-        Some(_) => "SELECT * FROM un_post WHERE thread_id = ? AND operation_time < ? AND operation_time NOT LIKE '2022-02-26 23:%' AND operation_time NOT LIKE '2022-02-16 01:%'",
-        None => "SELECT * FROM un_post WHERE post_id = ? AND operation_time < ? AND operation_time NOT LIKE '2022-02-26 23:%' AND operation_time NOT LIKE '2022-02-16 01:%'",
-    };
-    // 2022-02-16 01:XX -> rewinder
-    // 2022-02-26 23:XX -> rollwinder
-    let id_param = match thread_id {
-        Some(_) => thread_id,
-        None => post_id,
-    };
-    let admin_logs = vault
+    only_good: bool,
+    year: Option<String>,
+) -> Option<DiscoveredThread> {
+    let thread = vault
         .run(move |c| {
-            c.prepare(sql)?
-                .query_map(params![id_param, datetime], |r| {
-                    Ok(AdminLog::Post {
-                        thread_id: r.get(0)?,
-                        post_id: r.get(1)?,
-                        title: r.get(2)?,
-                        content_preview: r.get(3)?,
-                        media: r.get(4)?,
-                        username: r.get(5)?,
-                        post_time: r.get(6)?,
-                        operation: r.get(7)?,
-                        operator: r.get(8)?,
-                        operation_time: r.get(9)?,
-                    })
-                })?
-                .collect::<Result<Vec<AdminLog>, _>>()
+            let deleted_threads = get_deleted_threads(c, &get_datetime_sql_param(None))?;
+            let sql = format!(
+                "SELECT t.id, t.title, t.user_id, p.time, t.reply_num, t.is_good, p.content
+                     FROM pr_thread AS t
+                     JOIN pr_post AS p ON t.id = p.thread_id AND p.floor = 1
+                     WHERE (?1 = 0 OR t.is_good = 1)
+                     AND (?2 IS NULL OR substr(p.time, 1, 4) = ?2)
+                     AND t.id NOT IN ({})
+                     ORDER BY RANDOM()
+                     LIMIT 1",
+                deleted_threads
+                    .keys()
+                    .map(|thread_id| thread_id.to_string())
+                    .collect::<Vec<String>>()
+                    .join(",")
+            );
+            c.query_row(sql.as_str(), params![only_good, year], |r| {
+                Ok(DiscoveredThread {
+                    thread_id: r.get(0)?,
+                    title: r.get(1)?,
+                    op_user_id: r.get(2)?,
+                    created_time: r.get(3)?,
+                    reply_num: r.get(4)?,
+                    is_good: r.get(5)?,
+                    preview: get_preview(
+                        &serde_json::from_str(r.get::<usize, String>(6)?.as_str()).unwrap(),
+                    ),
+                })
+            })
         })
-        .await?;
-    Ok(admin_logs)
+        .await
+        .ok()?;
+    Some(thread)
 }
 
-async fn get_user_records(
+// pr_thread.reply_num is the value at scraping time, this is the one at time_machine_datetime
+async fn get_historical_reply_count(
     vault: &Vault,
-    user_id: i64,
-    time_machine_datetime: Option<String>,
+    thread_id: i64,
+    time_machine_datetime: String,
+) -> Result<ReplyCount, rusqlite::Error> {
+    let reply_count = vault
+        .run(move |c| -> Result<ReplyCount, rusqlite::Error> {
+            let posts = c
+                .prepare("SELECT id, floor FROM pr_post WHERE thread_id = ? AND time < ?")?
+                .query_map(params![thread_id, time_machine_datetime], |r| {
+                    Ok((r.get::<usize, i64>(0)?, r.get::<usize, i32>(1)?))
+                })?
+                .collect::<Result<Vec<(i64, i32)>, _>>()?;
+            let comments = c
+                .prepare(
+                    "SELECT pr_comment.id, post_id
+                         FROM pr_comment
+                         JOIN pr_post ON pr_comment.post_id = pr_post.id
+                         WHERE thread_id = ? AND pr_comment.time < ?",
+                )?
+                .query_map(params![thread_id, time_machine_datetime], |r| {
+                    Ok((r.get::<usize, i64>(0)?, r.get::<usize, i64>(1)?))
+                })?
+                .collect::<Result<Vec<(i64, i64)>, _>>()?;
+
+            // what get_posts and get_comments hide in the public view doesn't count,
+            // neither do comments under a hidden post
+            let post_ids = posts
+                .iter()
+                .map(|(post_id, _)| *post_id)
+                .collect::<Vec<i64>>();
+            let comment_ids = comments
+                .iter()
+                .map(|(comment_id, _)| *comment_id)
+                .collect::<Vec<i64>>();
+            let deleted_posts = get_deletion_logs(c, &post_ids, &time_machine_datetime)?;
+            let deleted_comments = get_deletion_logs(c, &comment_ids, &time_machine_datetime)?;
+            Ok(ReplyCount {
+                reply_num: posts
+                    .iter()
+                    .filter(|(post_id, floor)| *floor > 1 && !deleted_posts.contains_key(post_id))
+                    .count() as u32,
+                comment_num: comments
+                    .iter()
+                    .filter(|(comment_id, post_id)| {
+                        !deleted_comments.contains_key(comment_id)
+                            && !deleted_posts.contains_key(post_id)
+                    })
+                    .count() as u32,
+            })
+        })
+        .await?;
+    Ok(reply_count)
+}
+
+async fn get_thread_statistics(
+    vault: &Vault,
+    thread_id: i64,
+    time_machine_datetime: Option<String>,
+) -> Result<ThreadStatistics, rusqlite::Error> {
+    let datetime = get_datetime_sql_param(time_machine_datetime);
+    let statistics = vault
+        .run(move |c| {
+            let contents = c
+                .prepare(
+                    "SELECT user_id, content, time FROM pr_post WHERE thread_id = ?1 AND time < ?2
+                         UNION ALL
+                         SELECT pr_comment.user_id, pr_comment.content, pr_comment.time
+                         FROM pr_comment
+                         JOIN pr_post ON pr_comment.post_id = pr_post.id
+                         WHERE thread_id = ?1 AND pr_comment.time < ?2",
+                )?
+                .query_map(params![thread_id, datetime], |r| {
+                    Ok((
+                        r.get::<usize, i64>(0)?,
+                        serde_json::from_str::<serde_json::Value>(r.get::<usize, String>(1)?.as_str()).unwrap(),
+                        r.get::<usize, String>(2)?,
+                    ))
+                })?
+                .collect::<Result<Vec<(i64, serde_json::Value, String)>, _>>()?;
+            let (created_time, post_num) = c.query_row(
+                "SELECT MIN(time), COUNT(*) FROM pr_post WHERE thread_id = ? AND time < ?",
+                params![thread_id, datetime],
+                |r| Ok((r.get::<usize, Option<String>>(0)?, r.get::<usize, u32>(1)?)),
+            )?;
+            let (thread_logs, post_logs): (Vec<AdminLog>, Vec<AdminLog>) = c
+                .prepare(format!("SELECT * FROM un_post WHERE thread_id = ? AND operation_time < ? AND {} ORDER BY operation_time", get_showdown_exclusion_sql()).as_str())?
+                .query_map(params![thread_id, datetime], get_post_admin_log)?
+                .collect::<Result<Vec<AdminLog>, _>>()?
+                .into_iter()
+                .partition(|admin_log| matches!(admin_log, AdminLog::Post { post_id: None, .. }));
+            // replayed per level, restored ones don't count
+            let deletion_num = (replay_deletions(thread_logs).len() + replay_deletions(post_logs).len()) as u32;
+
+            let mut participants = contents.iter().map(|(user_id, _, _)| *user_id).collect::<Vec<i64>>();
+            participants.sort_unstable();
+            participants.dedup();
+            Ok::<_, rusqlite::Error>(ThreadStatistics {
+                created_time,
+                last_reply_time: contents.iter().map(|(_, _, time)| time.clone()).max(),
+                post_num,
+                comment_num: contents.len() as u32 - post_num,
+                participant_num: participants.len() as u32,
+                image_num: contents
+                    .iter()
+                    .map(|(_, content, _)| content::count_images(content) as u32)
+                    .sum(),
+                deletion_num,
+            })
+        })
+        .await?;
+    Ok(statistics)
+}
+
+// maps a SELECT * FROM un_post row
+fn get_post_admin_log(r: &rusqlite::Row) -> rusqlite::Result<AdminLog> {
+    Ok(AdminLog::Post {
+        thread_id: r.get(0)?,
+        post_id: r.get(1)?,
+        title: r.get(2)?,
+        content_preview: r.get(3)?,
+        media: r.get(4)?,
+        media_entries: get_media_entries(r.get::<usize, Option<String>>(4)?.as_deref()),
+        username: r.get(5)?,
+        post_time: r.get(6)?,
+        operation: r.get(7)?,
+        operator: r.get(8)?,
+        operation_time: r.get(9)?,
+    })
+}
+
+// feeds un_post logs, already in operation_time order, to the visibility state machine
+// keyed by post_id, or by thread_id for thread-level logs, so don't mix the two levels in one call
+// returns the 删贴 log of everything still deleted at the end
+fn replay_deletions(admin_logs: Vec<AdminLog>) -> BTreeMap<i64, AdminLog> {
+    visibility::replay(
+        admin_logs
+            .into_iter()
+            .filter_map(|admin_log| match &admin_log {
+                AdminLog::Post {
+                    thread_id,
+                    post_id,
+                    operation,
+                    ..
+                } => Some((post_id.unwrap_or(*thread_id), operation.clone(), admin_log)),
+                _ => None,
+            }),
+    )
+}
+
+fn get_deleted_threads(
+    c: &rusqlite::Connection,
+    datetime: &str,
+) -> Result<BTreeMap<i64, AdminLog>, rusqlite::Error> {
+    let admin_logs = c
+        .prepare(
+            format!("SELECT * FROM un_post WHERE post_id IS NULL AND operation_time < ? AND {} ORDER BY operation_time", get_showdown_exclusion_sql()).as_str(),
+        )?
+        .query_map(params![datetime], get_post_admin_log)?
+        .collect::<Result<Vec<AdminLog>, _>>()?;
+    let mut deletion_logs = replay_deletions(admin_logs);
+    link_media_mirror(c, deletion_logs.values_mut())?; // only the survivors, the rest are thrown away
+    Ok(deletion_logs)
+}
+
+// threads missing from pr_thread but posted before datetime according to un_post
+// keyword is matched against the title and content_preview, the only text we have
+// the earliest row stands in for floor 1, the thread-level log sorts first if there is one
+fn get_ghost_threads(
+    c: &rusqlite::Connection,
+    datetime: &str,
+    keyword: &str,
+) -> Result<Vec<Thread>, rusqlite::Error> {
+    let rows = c
+        .prepare(
+            "SELECT thread_id, post_id, title, content_preview, username, post_time
+                 FROM un_post
+                 WHERE thread_id NOT IN (SELECT id FROM pr_thread)
+                 AND post_time < ?1
+                 AND thread_id IN (
+                     SELECT thread_id FROM un_post WHERE title LIKE ?2 OR content_preview LIKE ?2
+                 )
+                 ORDER BY post_time, post_id IS NOT NULL",
+        )?
+        .query_map(params![datetime, keyword], |r| {
+            Ok((
+                r.get::<usize, i64>(0)?,
+                r.get::<usize, Option<i64>>(1)?,
+                r.get::<usize, String>(2)?,
+                r.get::<usize, String>(3)?,
+                r.get::<usize, String>(4)?,
+                r.get::<usize, String>(5)?,
+            ))
+        })?
+        .collect::<Result<Vec<(i64, Option<i64>, String, String, String, String)>, _>>()?;
+
+    let mut ghost_threads: BTreeMap<i64, (Thread, Vec<Option<i64>>)> = BTreeMap::new();
+    for (thread_id, post_id, title, content_preview, username, post_time) in rows {
+        let (thread, post_ids) = ghost_threads.entry(thread_id).or_insert_with(|| {
+            (
+                Thread {
+                    thread_id,
+                    op_user_id: None,
+                    title: title.clone(),
+                    user_id: None,
+                    time: post_time.clone(),
+                    reply_num: 0,
+                    is_good: false,
+                    op_post_content: json!([{"type": "text", "content": content_preview}]),
+                    created_time: post_time.clone(),
+                    reconstructed: true,
+                    op_username: Some(username.clone()),
+                    last_reply_username: None,
+                },
+                Vec::new(),
+            )
+        });
+        if post_ids.contains(&post_id) {
+            continue; // the same post deleted, restored and deleted again
+        }
+        post_ids.push(post_id);
+        thread.time = post_time;
+        thread.last_reply_username = Some(username);
+    }
+
+    let mut threads: Vec<Thread> = Vec::with_capacity(ghost_threads.len());
+    for (_, (mut thread, post_ids)) in ghost_threads {
+        thread.reply_num = post_ids.len() as i32 - 1; // like pr_thread.reply_num, floor 1 excluded
+        if let Some(username) = &thread.op_username {
+            thread.op_user_id = get_user_id_by_name(c, username)?;
+        }
+        if let Some(username) = &thread.last_reply_username {
+            thread.user_id = get_user_id_by_name(c, username)?;
+        }
+        threads.push(thread);
+    }
+    Ok(threads)
+}
+
+// also for comments, un_post keeps comment ids in post_id
+fn get_moderations(
+    c: &rusqlite::Connection,
+    post_ids: &[i64],
+    datetime: &str,
+) -> Result<BTreeMap<i64, Moderation>, rusqlite::Error> {
+    let sql = format!(
+        "SELECT post_id, operation, operator, operation_time FROM un_post WHERE post_id IN ({}) AND operation_time < ? AND {} ORDER BY operation_time",
+        post_ids
+            .iter()
+            .map(|post_id| post_id.to_string())
+            .collect::<Vec<String>>()
+            .join(","),
+        get_showdown_exclusion_sql()
+    );
+    // later operations overwrite earlier ones
+    let moderations = c
+        .prepare(sql.as_str())?
+        .query_map(params![datetime], |r| {
+            let operation: String = r.get(1)?;
+            Ok((
+                r.get::<usize, i64>(0)?,
+                Moderation {
+                    operation_code: Operation::parse(&operation).code().to_string(),
+                    operation_label: Operation::parse(&operation).label(Lang::ZhCn).to_string(),
+                    operation,
+                    operator: r.get(2)?,
+                    operation_time: r.get(3)?,
+                },
+            ))
+        })?
+        .collect::<Result<BTreeMap<i64, Moderation>, _>>()?;
+    Ok(moderations)
+}
+
+// works for both posts and comments, un_post keeps comment ids in post_id as well
+fn get_deletion_logs(
+    c: &rusqlite::Connection,
+    post_ids: &[i64],
+    datetime: &str,
+) -> Result<BTreeMap<i64, AdminLog>, rusqlite::Error> {
+    let sql = format!(
+        "SELECT * FROM un_post WHERE post_id IN ({}) AND operation_time < ? AND {} ORDER BY operation_time",
+        post_ids
+            .iter()
+            .map(|post_id| post_id.to_string())
+            .collect::<Vec<String>>()
+            .join(","),
+        get_showdown_exclusion_sql()
+    );
+    let admin_logs = c
+        .prepare(sql.as_str())?
+        .query_map(params![datetime], get_post_admin_log)?
+        .collect::<Result<Vec<AdminLog>, _>>()?;
+    let mut deletion_logs = replay_deletions(admin_logs);
+    link_media_mirror(c, deletion_logs.values_mut())?; // only the survivors, the rest are thrown away
+    Ok(deletion_logs)
+}
+
+async fn get_posts(
+    vault: &Vault,
+    thread_id: i64,
+    time_machine_datetime: Option<String>,
+    order: PostOrder,
+    view: View,
+) -> Result<Vec<Post>, rusqlite::Error> {
+    let is_time_machine = time_machine_datetime.is_some();
+    let sql = match order {
+        PostOrder::Floor => "SELECT * FROM pr_post WHERE thread_id = ? AND time < ? ORDER BY floor",
+        PostOrder::FloorDesc => "SELECT * FROM pr_post WHERE thread_id = ? AND time < ? ORDER BY floor DESC",
+        PostOrder::MostDiscussed => "SELECT * FROM pr_post WHERE thread_id = ? AND time < ? ORDER BY comment_num DESC, floor",
+    };
+    let datetime = get_datetime_sql_param(time_machine_datetime);
+    let posts = vault
+        .run(move |c| -> Result<Vec<Post>, rusqlite::Error> {
+            let mut posts = c
+                .prepare(sql)?
+                .query_map(params![thread_id, datetime], |r| {
+                    Ok(Post {
+                        post_id: r.get(0)?,
+                        floor: r.get(1)?,
+                        user_id: r.get(2)?,
+                        content: serde_json::from_str(r.get::<usize, String>(3)?.as_str()).unwrap(), // so ugly
+                        time: r.get(4)?,
+                        comment_num: r.get(5)?,
+                        signature: r.get(6)?,
+                        tail: r.get(7)?,
+                        deletion: None,
+                        moderation: None,
+                    })
+                })?
+                .collect::<Result<Vec<Post>, _>>()?;
+            let post_ids = posts.iter().map(|post| post.post_id).collect::<Vec<i64>>();
+            let mut moderations = get_moderations(c, &post_ids, &datetime)?;
+            for post in posts.iter_mut() {
+                post.moderation = moderations.remove(&post.post_id);
+            }
+            if !is_time_machine {
+                return Ok(posts);
+            }
+            let mut deletion_logs = get_deletion_logs(c, &post_ids, &datetime)?;
+            Ok(posts
+                .into_iter()
+                .filter_map(
+                    |mut post| match (deletion_logs.remove(&post.post_id), view) {
+                        (None, _) => Some(post),
+                        (Some(_), View::Public) => None,
+                        (Some(deletion_log), View::Moderator) => {
+                            post.deletion = Some(get_deletion_log(deletion_log));
+                            Some(post)
+                        }
+                    },
+                )
+                .collect())
+        })
+        .await?;
+    Ok(posts)
+}
+
+fn get_user_id_by_name(
+    c: &rusqlite::Connection,
+    name: &str,
+) -> Result<Option<i64>, rusqlite::Error> {
+    for sql in [
+        "SELECT id FROM pr_user WHERE username = ?",
+        "SELECT id FROM pr_user WHERE nickname = ?", // nicknames are not unique
+    ] {
+        let user_ids = c
+            .prepare(sql)?
+            .query_map(params![name], |r| r.get::<usize, i64>(0))?
+            .collect::<Result<Vec<i64>, _>>()?;
+        if user_ids.len() == 1 {
+            return Ok(Some(user_ids[0]));
+        }
+    }
+    Ok(None)
+}
+
+// the same rule as get_user_id_by_name, loaded once for resolving many names
+// usernames are unique, nicknames are not: only resolve a nickname if exactly one user has it
+struct UserNames {
+    usernames: BTreeMap<String, i64>,
+    nicknames: BTreeMap<String, Option<i64>>,
+}
+
+impl UserNames {
+    fn resolve(&self, name: &str) -> Option<i64> {
+        self.usernames
+            .get(name)
+            .copied()
+            .or_else(|| self.nicknames.get(name).copied().flatten())
+    }
+}
+
+fn get_user_names(c: &rusqlite::Connection) -> Result<UserNames, rusqlite::Error> {
+    let mut user_names = UserNames {
+        usernames: BTreeMap::new(),
+        nicknames: BTreeMap::new(),
+    };
+    let mut stmt = c.prepare("SELECT id, username, nickname FROM pr_user")?;
+    let mut rows = stmt.query(params![])?;
+    while let Some(r) = rows.next()? {
+        let user_id = r.get::<usize, i64>(0)?;
+        if let Some(username) = r.get::<usize, Option<String>>(1)? {
+            user_names.usernames.insert(username, user_id);
+        }
+        user_names
+            .nicknames
+            .entry(r.get::<usize, String>(2)?)
+            .and_modify(|existing| *existing = None)
+            .or_insert(Some(user_id));
+    }
+    Ok(user_names)
+}
+
+// rows are (comment, username, nickname) ordered by time, so a reply can only point backwards
+fn resolve_reply_targets(
+    c: &rusqlite::Connection,
+    rows: Vec<(Comment, Option<String>, Option<String>)>,
+) -> Result<Vec<Comment>, rusqlite::Error> {
+    let mut comments: Vec<Comment> = Vec::with_capacity(rows.len());
+    for (i, (comment, _, _)) in rows.iter().enumerate() {
+        let mut comment = comment.clone();
+        if let Some(target) = content::extract_reply_to(&comment.content) {
+            let candidates = rows[..i]
+                .iter()
+                .filter(|(earlier, username, nickname)| match target.user_id {
+                    Some(user_id) => earlier.user_id == user_id,
+                    None => {
+                        username.as_ref() == Some(&target.name)
+                            || nickname.as_ref() == Some(&target.name)
+                    }
+                })
+                .map(|(earlier, _, _)| earlier)
+                .collect::<Vec<&Comment>>();
+            // earlier commenters sharing a nickname with different user ids leave it to get_user_id_by_name
+            comment.reply_to_user_id = match (target.user_id, candidates.first()) {
+                (Some(user_id), _) => Some(user_id),
+                (None, Some(first))
+                    if candidates
+                        .iter()
+                        .all(|earlier| earlier.user_id == first.user_id) =>
+                {
+                    Some(first.user_id)
+                }
+                (None, _) => get_user_id_by_name(c, &target.name)?,
+            };
+            comment.reply_to_comment_id = match candidates.as_slice() {
+                [only] => Some(only.comment_id),
+                _ => None,
+            };
+        }
+        comments.push(comment);
+    }
+    Ok(comments)
+}
+
+async fn get_comments(
+    vault: &Vault,
+    post_id: i64,
+    time_machine_datetime: Option<String>,
+    order: CommentOrder,
+    view: View,
+) -> Result<Vec<Comment>, rusqlite::Error> {
+    let is_time_machine = time_machine_datetime.is_some();
+    let datetime = get_datetime_sql_param(time_machine_datetime);
+    let comments = vault
+        .run(move |c| -> Result<Vec<Comment>, rusqlite::Error> {
+            let rows = c
+                .prepare(
+                    "SELECT pr_comment.id, user_id, content, time, username, nickname
+                         FROM pr_comment
+                         LEFT JOIN pr_user
+                         ON pr_comment.user_id = pr_user.id
+                         WHERE post_id = ? AND time < ?
+                         ORDER BY time",
+                )?
+                .query_map(params![post_id, datetime], |r| {
+                    Ok((
+                        Comment {
+                            comment_id: r.get(0)?,
+                            user_id: r.get(1)?,
+                            content: serde_json::from_str(r.get::<usize, String>(2)?.as_str())
+                                .unwrap(),
+                            time: r.get(3)?,
+                            reply_to_user_id: None,
+                            reply_to_comment_id: None,
+                            deletion: None,
+                            moderation: None,
+                        },
+                        r.get(4)?,
+                        r.get(5)?,
+                    ))
+                })?
+                .collect::<Result<Vec<(Comment, Option<String>, Option<String>)>, _>>()?;
+            let comment_ids = rows
+                .iter()
+                .map(|(comment, _, _)| comment.comment_id)
+                .collect::<Vec<i64>>();
+            let mut moderations = get_moderations(c, &comment_ids, &datetime)?;
+            let rows = match is_time_machine {
+                false => rows,
+                true => {
+                    // comments under a deleted floor go with it, in the moderator view they carry the floor's log
+                    let floor_deletion_log =
+                        get_deletion_logs(c, &[post_id], &datetime)?.remove(&post_id);
+                    if let (Some(_), View::Public) = (&floor_deletion_log, view) {
+                        return Ok(Vec::new());
+                    }
+                    let mut deletion_logs = get_deletion_logs(c, &comment_ids, &datetime)?;
+                    rows.into_iter()
+                        .filter_map(|(mut comment, username, nickname)| {
+                            let deletion_log = deletion_logs
+                                .remove(&comment.comment_id)
+                                .or_else(|| floor_deletion_log.clone());
+                            match (deletion_log, view) {
+                                (None, _) => Some((comment, username, nickname)),
+                                (Some(_), View::Public) => None,
+                                (Some(deletion_log), View::Moderator) => {
+                                    comment.deletion = Some(get_deletion_log(deletion_log));
+                                    Some((comment, username, nickname))
+                                }
+                            }
+                        })
+                        .collect()
+                }
+            };
+            // after the deletion filter, so a reply never points at a hidden comment
+            let mut comments = resolve_reply_targets(c, rows)?;
+            for comment in comments.iter_mut() {
+                comment.moderation = moderations.remove(&comment.comment_id);
+            }
+            Ok(comments)
+        })
+        .await?;
+    // reply targets are resolved in time order, so reverse afterwards instead of in sql
+    let comments = match order {
+        CommentOrder::Time => comments,
+        CommentOrder::TimeDesc => comments.into_iter().rev().collect(),
+    };
+    Ok(comments)
+}
+
+async fn get_post_related_admin_logs(
+    vault: &Vault,
+    thread_id: Option<i64>,
+    post_id: Option<i64>,
+    time_machine_datetime: Option<String>,
+) -> Result<Vec<AdminLog>, rusqlite::Error> {
+    let datetime = get_datetime_sql_param(time_machine_datetime);
+    let sql = match thread_id {
+        Some(_) => format!(
+            "SELECT * FROM un_post WHERE thread_id = ? AND operation_time < ? AND {}",
+            get_showdown_exclusion_sql()
+        ),
+        None => format!(
+            "SELECT * FROM un_post WHERE post_id = ? AND operation_time < ? AND {}",
+            get_showdown_exclusion_sql()
+        ),
+    };
+    let id_param = match thread_id {
+        Some(_) => thread_id,
+        None => post_id,
+    };
+    let admin_logs = vault
+        .run(move |c| {
+            let mut admin_logs = c
+                .prepare(sql.as_str())?
+                .query_map(params![id_param, datetime], get_post_admin_log)?
+                .collect::<Result<Vec<AdminLog>, _>>()?;
+            link_media_mirror(c, &mut admin_logs)?;
+            Ok::<_, rusqlite::Error>(admin_logs)
+        })
+        .await?;
+    Ok(admin_logs)
+}
+
+// one pseudo-post per thread-level log and per post_id, ordered by post_time
+// the showdown logs are kept here, their previews are as genuine as any
+async fn get_ghost_posts(
+    vault: &Vault,
+    thread_id: i64,
+    time_machine_datetime: Option<String>,
+) -> Result<Vec<GhostPost>, rusqlite::Error> {
+    let datetime = get_datetime_sql_param(time_machine_datetime);
+    let ghost_posts = vault
+        .run(move |c| -> Result<Vec<GhostPost>, rusqlite::Error> {
+            let rows = c
+                .prepare(
+                    "SELECT post_id, username, title, content_preview, media, post_time
+                         FROM un_post
+                         WHERE thread_id = ? AND post_time < ?
+                         ORDER BY post_time, post_id IS NOT NULL, operation_time",
+                )?
+                .query_map(params![thread_id, datetime], |r| {
+                    Ok(GhostPost {
+                        post_id: r.get(0)?,
+                        user_id: None,
+                        username: r.get(1)?,
+                        title: r.get(2)?,
+                        content_preview: r.get(3)?,
+                        media: r.get(4)?,
+                        time: r.get(5)?,
+                        reconstructed: true,
+                    })
+                })?
+                .collect::<Result<Vec<GhostPost>, _>>()?;
+
+            let mut ghost_posts: Vec<GhostPost> = Vec::new();
+            for mut row in rows {
+                if ghost_posts.iter().any(|post| post.post_id == row.post_id) {
+                    continue; // the same post deleted, restored and deleted again
+                }
+                row.user_id = get_user_id_by_name(c, &row.username)?;
+                ghost_posts.push(row);
+            }
+            Ok(ghost_posts)
+        })
+        .await?;
+    Ok(ghost_posts)
+}
+
+// thread_id alone gives the thread itself, post_id gives a post or a comment
+async fn get_visibility_timeline(
+    vault: &Vault,
+    thread_id: Option<i64>,
+    post_id: Option<i64>,
+    time_machine_datetime: Option<String>,
+    lang: Lang,
+) -> Result<Vec<VisibilityChange>, rusqlite::Error> {
+    let mut admin_logs =
+        get_post_related_admin_logs(vault, thread_id, post_id, time_machine_datetime).await?;
+    admin_logs.retain(|admin_log| {
+        matches!(admin_log, AdminLog::Post { post_id: log_post_id, .. } if *log_post_id == post_id)
+    });
+    admin_logs.sort_by(|a, b| match (a, b) {
+        (
+            AdminLog::Post {
+                operation_time: a, ..
+            },
+            AdminLog::Post {
+                operation_time: b, ..
+            },
+        ) => a.cmp(b),
+        _ => std::cmp::Ordering::Equal,
+    });
+
+    let mut visibility = Visibility::Visible;
+    let mut timeline: Vec<VisibilityChange> = Vec::new();
+    for admin_log in admin_logs {
+        if let AdminLog::Post {
+            operation,
+            operator,
+            operation_time,
+            ..
+        } = admin_log
+        {
+            visibility = visibility.apply(&operation);
+            timeline.push(VisibilityChange {
+                operation_code: Operation::parse(&operation).code().to_string(),
+                operation_label: Operation::parse(&operation).label(lang).to_string(),
+                operation,
+                operator,
+                operation_time,
+                visibility: visibility.code().to_string(),
+            });
+        }
+    }
+    Ok(timeline)
+}
+
+async fn get_user_records(
+    vault: &Vault,
+    user_id: i64,
+    time_machine_datetime: Option<String>,
 ) -> Result<Vec<UserRecord>, rusqlite::Error> {
     let datetime = get_datetime_sql_param(time_machine_datetime);
     let user_records = vault
@@ -434,36 +1656,574 @@ async fn get_user_records(
     Ok(user_records)
 }
 
+// shared with the link-check command, which has no rocket around it
+fn collect_link_citations(
+    c: &rusqlite::Connection,
+) -> Result<Vec<(String, LinkCitation)>, rusqlite::Error> {
+    let rows = c
+        .prepare(
+            "SELECT thread_id, id AS post_id, NULL AS comment_id, content, time
+                 FROM pr_post
+                 WHERE content LIKE '%http%' OR content LIKE '%\"url\"%'
+                 UNION ALL
+                 SELECT pr_post.thread_id, pr_post.id, pr_comment.id, pr_comment.content, pr_comment.time
+                 FROM pr_comment
+                 JOIN pr_post
+                 ON pr_comment.post_id = pr_post.id
+                 WHERE pr_comment.content LIKE '%http%' OR pr_comment.content LIKE '%\"url\"%'
+                 ORDER BY time",
+        )?
+        .query_map(params![], |r| {
+            Ok((
+                LinkCitation {
+                    thread_id: r.get(0)?,
+                    post_id: r.get(1)?,
+                    comment_id: r.get(2)?,
+                    time: r.get(4)?,
+                },
+                serde_json::from_str(r.get::<usize, String>(3)?.as_str()).unwrap(),
+            ))
+        })?
+        .collect::<Result<Vec<(LinkCitation, serde_json::Value)>, _>>()?;
+    let mut citations = Vec::new();
+    for (citation, content) in rows {
+        for url in content::extract_links(&content) {
+            citations.push((
+                url,
+                LinkCitation {
+                    thread_id: citation.thread_id,
+                    post_id: citation.post_id,
+                    comment_id: citation.comment_id,
+                    time: citation.time.clone(),
+                },
+            ));
+        }
+    }
+    Ok(citations)
+}
+
+async fn get_outbound_links(vault: &Vault) -> Result<Vec<LinkDomain>, rusqlite::Error> {
+    let (citations, statuses) = vault
+        .run(|c| {
+            let citations = collect_link_citations(c)?;
+            // the table only exists after link-check has been run
+            let statuses = match c.prepare("SELECT url, status FROM vault_link_check") {
+                Ok(mut stmt) => stmt
+                    .query_map(params![], |r| {
+                        Ok((r.get::<usize, String>(0)?, r.get::<usize, u16>(1)?))
+                    })?
+                    .collect::<Result<BTreeMap<String, u16>, _>>()?,
+                Err(_) => BTreeMap::new(),
+            };
+            Ok::<_, rusqlite::Error>((citations, statuses))
+        })
+        .await?;
+
+    let mut links: BTreeMap<String, OutboundLink> = BTreeMap::new();
+    for (url, citation) in citations {
+        let link = links.entry(url.clone()).or_insert_with(|| OutboundLink {
+            status: statuses.get(&url).copied(),
+            dead: statuses
+                .get(&url)
+                .map(|status| *status == 0 || *status >= 400),
+            url,
+            count: 0,
+            first_seen: citation.time.clone(),
+            last_seen: citation.time.clone(),
+            citations: Vec::new(),
+        });
+        link.count += 1;
+        link.last_seen = citation.time.clone(); // citations are ordered by time
+        link.citations.push(citation);
+    }
+
+    let mut domains: BTreeMap<String, LinkDomain> = BTreeMap::new();
+    for (_, link) in links {
+        let domain = content::get_domain(&link.url);
+        let entry = domains.entry(domain.clone()).or_insert_with(|| LinkDomain {
+            domain,
+            count: 0,
+            first_seen: link.first_seen.clone(),
+            last_seen: link.last_seen.clone(),
+            links: Vec::new(),
+        });
+        entry.count += link.count;
+        if link.first_seen < entry.first_seen {
+            entry.first_seen = link.first_seen.clone();
+        }
+        if link.last_seen > entry.last_seen {
+            entry.last_seen = link.last_seen.clone();
+        }
+        entry.links.push(link);
+    }
+
+    let mut domains = domains.into_values().collect::<Vec<LinkDomain>>();
+    domains.sort_by_key(|d| std::cmp::Reverse(d.count));
+    Ok(domains)
+}
+
+// finds every post and comment carrying a username block, i.e. an @mention
+fn collect_mentioning_records(
+    c: &rusqlite::Connection,
+    datetime: &str,
+) -> Result<Vec<(MentionRecord, serde_json::Value)>, rusqlite::Error> {
+    c.prepare(
+        "SELECT pr_post.user_id, thread_id, pr_thread.title, pr_post.id, floor, content, NULL, NULL, time
+             FROM pr_post
+             JOIN pr_thread
+             ON pr_post.thread_id = pr_thread.id
+             WHERE pr_post.content LIKE '%\"username\"%'
+             AND pr_post.time < ?1
+             UNION
+             SELECT pr_comment.user_id, pr_thread.id, pr_thread.title, post_id, pr_post.floor, pr_post.content, pr_comment.id, pr_comment.content, pr_comment.time
+             FROM pr_comment
+             JOIN pr_post
+             ON pr_comment.post_id = pr_post.id
+             JOIN pr_thread
+             ON pr_post.thread_id = pr_thread.id
+             WHERE pr_comment.content LIKE '%\"username\"%'
+             AND pr_comment.time < ?1
+             ORDER BY time DESC",
+    )?
+    .query_map(params![datetime], |r| {
+        let post_content: serde_json::Value =
+            serde_json::from_str(r.get::<usize, String>(5)?.as_str()).unwrap();
+        match r.get::<usize, Option<i64>>(6)? {
+            None => Ok((
+                MentionRecord {
+                    user_id: r.get(0)?,
+                    record: UserRecord::Post {
+                        _type: "post".to_string(),
+                        thread_id: r.get(1)?,
+                        title: r.get(2)?,
+                        post_id: r.get(3)?,
+                        floor: r.get(4)?,
+                        post_content: post_content.clone(),
+                        time: r.get(8)?,
+                    },
+                },
+                post_content,
+            )),
+            Some(_) => {
+                let comment_content: serde_json::Value =
+                    serde_json::from_str(r.get::<usize, String>(7)?.as_str()).unwrap();
+                Ok((
+                    MentionRecord {
+                        user_id: r.get(0)?,
+                        record: UserRecord::Comment {
+                            _type: "comment".to_string(),
+                            thread_id: r.get(1)?,
+                            title: r.get(2)?,
+                            post_id: r.get(3)?,
+                            floor: r.get(4)?,
+                            post_content,
+                            comment_id: r.get(6)?,
+                            comment_content: comment_content.clone(),
+                            time: r.get(8)?,
+                        },
+                    },
+                    comment_content,
+                ))
+            }
+        }
+    })?
+    .collect::<Result<Vec<(MentionRecord, serde_json::Value)>, _>>()
+}
+
+async fn get_mentions(
+    vault: &Vault,
+    user: &User,
+    time_machine_datetime: Option<String>,
+) -> Result<Vec<MentionRecord>, rusqlite::Error> {
+    let datetime = get_datetime_sql_param(time_machine_datetime);
+    let user_id = user.user_id;
+    let mentions = vault
+        .run(move |c| -> Result<Vec<MentionRecord>, rusqlite::Error> {
+            let records = collect_mentioning_records(c, &datetime)?;
+            let user_names = get_user_names(c)?;
+            let mut mentions: Vec<MentionRecord> = Vec::new();
+            for (record, content) in records {
+                for mention in content::extract_mentions(&content) {
+                    let mentioned_user_id = mention
+                        .user_id
+                        .or_else(|| user_names.resolve(&mention.name));
+                    if mentioned_user_id == Some(user_id) {
+                        mentions.push(record);
+                        break;
+                    }
+                }
+            }
+            Ok(mentions)
+        })
+        .await?;
+    Ok(mentions)
+}
+
+async fn get_mention_graph(
+    vault: &Vault,
+    time_machine_datetime: Option<String>,
+) -> Result<Vec<MentionEdge>, rusqlite::Error> {
+    let datetime = get_datetime_sql_param(time_machine_datetime);
+    let (records, user_names) = vault
+        .run(move |c| {
+            let records = collect_mentioning_records(c, &datetime)?;
+            let user_names = get_user_names(c)?;
+            Ok::<_, rusqlite::Error>((records, user_names))
+        })
+        .await?;
+
+    let mut edges: BTreeMap<(i64, String), MentionEdge> = BTreeMap::new();
+    for (record, content) in records {
+        for mention in content::extract_mentions(&content) {
+            let mentioned_user_id = mention
+                .user_id
+                .or_else(|| user_names.resolve(&mention.name));
+            edges
+                .entry((record.user_id, mention.name.clone()))
+                .or_insert(MentionEdge {
+                    user_id: record.user_id,
+                    mentioned_name: mention.name,
+                    mentioned_user_id,
+                    count: 0,
+                })
+                .count += 1;
+        }
+    }
+
+    let mut edges = edges.into_values().collect::<Vec<MentionEdge>>();
+    edges.sort_by_key(|edge| std::cmp::Reverse(edge.count));
+    Ok(edges)
+}
+
+async fn get_emoticon_usages(
+    vault: &Vault,
+    time_machine_datetime: Option<String>,
+) -> Result<Vec<EmoticonUsage>, rusqlite::Error> {
+    let datetime = get_datetime_sql_param(time_machine_datetime);
+    let contents = vault
+        .run(move |c| {
+            c.prepare(
+                "SELECT content FROM pr_post WHERE content LIKE '%\"emoticon\"%' AND time < ?1
+                     UNION ALL
+                     SELECT content FROM pr_comment WHERE content LIKE '%\"emoticon\"%' AND time < ?1",
+            )?
+            .query_map(params![datetime], |r| {
+                Ok(serde_json::from_str::<serde_json::Value>(r.get::<usize, String>(0)?.as_str()).unwrap())
+            })?
+            .collect::<Result<Vec<serde_json::Value>, _>>()
+        })
+        .await?;
+
+    let mut usages: BTreeMap<String, EmoticonUsage> = emoticon::EMOTICONS
+        .iter()
+        .map(|e| {
+            (
+                e.code.to_string(),
+                EmoticonUsage {
+                    code: e.code.to_string(),
+                    name: Some(e.name.to_string()),
+                    asset: Some(e.asset()),
+                    unicode: e.unicode.map(|u| u.to_string()),
+                    fallback: e.fallback(),
+                    count: 0,
+                },
+            )
+        })
+        .collect();
+    for content in contents {
+        for used in content::extract_emoticons(&content) {
+            usages
+                .entry(used.code.clone())
+                .or_insert(EmoticonUsage {
+                    fallback: format!("[{}]", used.description.as_ref().unwrap_or(&used.code)),
+                    code: used.code,
+                    name: used.description,
+                    asset: None,
+                    unicode: None,
+                    count: 0,
+                })
+                .count += 1;
+        }
+    }
+
+    let mut usages = usages.into_values().collect::<Vec<EmoticonUsage>>();
+    usages.sort_by_key(|usage| std::cmp::Reverse(usage.count));
+    Ok(usages)
+}
+
+async fn get_tail_statistics(
+    vault: &Vault,
+    user_id: Option<i64>,
+    thread_id: Option<i64>,
+    time_machine_datetime: Option<String>,
+) -> Result<(Vec<TailMonth>, Vec<TailClient>), rusqlite::Error> {
+    let datetime = get_datetime_sql_param(time_machine_datetime);
+    let rows = vault
+        .run(move |c| {
+            c.prepare(
+                "SELECT substr(time, 1, 7) AS month, tail, COUNT(*)
+                     FROM pr_post
+                     WHERE time < ?1
+                     AND (?2 IS NULL OR user_id = ?2)
+                     AND (?3 IS NULL OR thread_id = ?3)
+                     GROUP BY month, tail
+                     ORDER BY month",
+            )?
+            .query_map(params![datetime, user_id, thread_id], |r| {
+                Ok((
+                    r.get::<usize, String>(0)?,
+                    r.get::<usize, Option<String>>(1)?,
+                    r.get::<usize, u32>(2)?,
+                ))
+            })?
+            .collect::<Result<Vec<(String, Option<String>, u32)>, _>>()
+        })
+        .await?;
+
+    let mut months: BTreeMap<String, TailMonth> = BTreeMap::new();
+    let mut clients: BTreeMap<String, TailClient> = BTreeMap::new();
+    for (month, tail, count) in rows {
+        let (platform, client) = tail::classify(tail.as_deref());
+        let entry = months.entry(month.clone()).or_insert(TailMonth {
+            month,
+            total: 0,
+            platforms: BTreeMap::new(),
+            shares: BTreeMap::new(),
+        });
+        entry.total += count;
+        *entry
+            .platforms
+            .entry(platform.code().to_string())
+            .or_insert(0) += count;
+        clients
+            .entry(client.clone())
+            .or_insert(TailClient {
+                client,
+                platform: platform.code().to_string(),
+                count: 0,
+            })
+            .count += count;
+    }
+
+    let mut months = months.into_values().collect::<Vec<TailMonth>>();
+    for month in &mut months {
+        month.shares = month
+            .platforms
+            .iter()
+            .map(|(platform, count)| (platform.clone(), *count as f64 / month.total as f64))
+            .collect();
+    }
+    let mut clients = clients.into_values().collect::<Vec<TailClient>>();
+    clients.sort_by_key(|client| std::cmp::Reverse(client.count));
+    Ok((months, clients))
+}
+
+fn get_next_month(month: &str) -> String {
+    let (year, month) = month.split_once('-').unwrap_or_default();
+    let (year, month) = (
+        year.parse::<u32>().unwrap_or_default(),
+        month.parse::<u32>().unwrap_or_default(),
+    );
+    match month {
+        12 => format!("{:04}-01", year + 1),
+        _ => format!("{:04}-{:02}", year, month + 1),
+    }
+}
+
+fn get_top_counts(counts: BTreeMap<String, u32>) -> Vec<(String, u32)> {
+    let mut counts = counts.into_iter().collect::<Vec<(String, u32)>>();
+    counts.sort_by_key(|(_, count)| std::cmp::Reverse(*count)); // stable, ties stay in key order
+    counts.truncate(MODERATOR_REPORT_TOP_NUM);
+    counts
+}
+
+async fn get_moderator_activities(
+    vault: &Vault,
+    time_machine_datetime: Option<String>,
+) -> Result<Vec<ModeratorActivity>, rusqlite::Error> {
+    let datetime = get_datetime_sql_param(time_machine_datetime);
+    let rows = vault
+        .run(move |c| {
+            c.prepare(
+                "SELECT operator, operation, operation_time, username FROM un_post WHERE operation_time < ?1
+                     UNION ALL
+                     SELECT operator, operation, operation_time, username FROM un_user WHERE operation_time < ?1
+                     UNION ALL
+                     SELECT operator, operation, operation_time, username FROM un_bawu WHERE operation_time < ?1 AND operator IS NOT NULL
+                     ORDER BY operation_time",
+            )?
+            .query_map(params![datetime], |r| {
+                Ok((
+                    r.get::<usize, String>(0)?,
+                    r.get::<usize, String>(1)?,
+                    r.get::<usize, String>(2)?,
+                    r.get::<usize, String>(3)?,
+                ))
+            })?
+            .collect::<Result<Vec<(String, String, String, String)>, _>>()
+        })
+        .await?;
+
+    let mut operators: BTreeMap<String, Vec<(String, String, String)>> = BTreeMap::new();
+    for (operator, operation, operation_time, username) in rows {
+        operators
+            .entry(operator)
+            .or_default()
+            .push((operation, operation_time, username));
+    }
+
+    let mut activities: Vec<ModeratorActivity> = Vec::with_capacity(operators.len());
+    for (operator, actions) in operators {
+        let mut operations: BTreeMap<String, u32> = BTreeMap::new();
+        let mut months: BTreeMap<String, u32> = BTreeMap::new();
+        let mut days: BTreeMap<String, u32> = BTreeMap::new();
+        let mut targets: BTreeMap<String, u32> = BTreeMap::new();
+        let mut showdown_counts: BTreeMap<String, u32> = BTreeMap::new();
+        for (operation, operation_time, username) in &actions {
+            *operations
+                .entry(Operation::parse(operation).code().to_string())
+                .or_insert(0) += 1;
+            *months
+                .entry(operation_time.chars().take(7).collect())
+                .or_insert(0) += 1;
+            *days
+                .entry(operation_time.chars().take(10).collect())
+                .or_insert(0) += 1;
+            *targets.entry(username.clone()).or_insert(0) += 1;
+            for (showdown, prefix) in SHOWDOWN_WINDOWS {
+                if operation_time.starts_with(prefix) {
+                    *showdown_counts.entry(showdown.to_string()).or_insert(0) += 1;
+                }
+            }
+        }
+
+        let mut active_periods: Vec<ActivePeriod> = Vec::new();
+        for (month, count) in months {
+            match active_periods.last_mut() {
+                Some(period) if get_next_month(&period.end) == month => {
+                    period.end = month;
+                    period.count += count;
+                }
+                _ => active_periods.push(ActivePeriod {
+                    start: month.clone(),
+                    end: month,
+                    count,
+                }),
+            }
+        }
+
+        let count = actions.len() as u32;
+        activities.push(ModeratorActivity {
+            operator,
+            count,
+            operations,
+            first_seen: actions[0].1.clone(), // rows are ordered by operation_time
+            last_seen: actions[actions.len() - 1].1.clone(),
+            active_periods,
+            busiest_days: get_top_counts(days)
+                .into_iter()
+                .map(|(day, count)| DayCount { day, count })
+                .collect(),
+            most_targeted_users: get_top_counts(targets)
+                .into_iter()
+                .map(|(username, count)| TargetCount { username, count })
+                .collect(),
+            showdown_shares: SHOWDOWN_WINDOWS
+                .iter()
+                .map(|(showdown, _)| {
+                    (
+                        showdown.to_string(),
+                        *showdown_counts.get(*showdown).unwrap_or(&0) as f64 / count as f64,
+                    )
+                })
+                .collect(),
+        });
+    }
+    activities.sort_by_key(|activity| std::cmp::Reverse(activity.count));
+    Ok(activities)
+}
+
+async fn get_signature_history(
+    vault: &Vault,
+    user_id: i64,
+    time_machine_datetime: Option<String>,
+) -> Result<Vec<SignatureHistory>, rusqlite::Error> {
+    let datetime = get_datetime_sql_param(time_machine_datetime);
+    let rows = vault
+        .run(move |c| {
+            c.prepare(
+                "SELECT signature, thread_id, id, floor, time
+                     FROM pr_post
+                     WHERE user_id = ? AND signature IS NOT NULL AND time < ?
+                     ORDER BY time",
+            )?
+            .query_map(params![user_id, datetime], |r| {
+                Ok((
+                    r.get::<usize, String>(0)?,
+                    SignaturePost {
+                        thread_id: r.get(1)?,
+                        post_id: r.get(2)?,
+                        floor: r.get(3)?,
+                        time: r.get(4)?,
+                    },
+                ))
+            })?
+            .collect::<Result<Vec<(String, SignaturePost)>, _>>()
+        })
+        .await?;
+
+    // keep the order in which the signatures first appeared
+    let mut history: Vec<SignatureHistory> = Vec::new();
+    for (signature, post) in rows {
+        match history.iter_mut().find(|h| h.signature == signature) {
+            Some(entry) => {
+                entry.last_seen = post.time.clone();
+                entry.posts.push(post);
+            }
+            None => history.push(SignatureHistory {
+                signature,
+                first_seen: post.time.clone(),
+                last_seen: post.time.clone(),
+                posts: vec![post],
+            }),
+        }
+    }
+    Ok(history)
+}
+
 async fn get_admin_logs(
     vault: &Vault,
     category: AdminLogCategory,
     page: u32,
     hide_the_showdown: bool,
+    time_machine_datetime: Option<String>,
+    order: AdminLogOrder,
+    search_keyword: Option<String>,
 ) -> Result<Vec<AdminLog>, rusqlite::Error> {
+    let datetime = get_datetime_sql_param(time_machine_datetime);
+    let keyword = get_keyword_sql_param(search_keyword);
+    // rowid breaks the ties, plenty of logs share the same operation_time
+    let order_sql = match order {
+        AdminLogOrder::Time => "ORDER BY operation_time, rowid",
+        AdminLogOrder::TimeDesc => "ORDER BY operation_time DESC, rowid DESC",
+    };
     let admin_logs = match category {
         AdminLogCategory::Post => {
             let sql = match hide_the_showdown {
-                true => format!("{}{}", "SELECT * FROM un_post WHERE operation_time NOT LIKE '2022-02-26 23:%' AND operation_time NOT LIKE '2022-02-16 01:%' LIMIT ?,", ADMIN_LOG_CAPACITY_PER_PAGE),
-                false => format!("{}{}", "SELECT * FROM un_post LIMIT ?,", ADMIN_LOG_CAPACITY_PER_PAGE),
+                true => format!("SELECT * FROM un_post WHERE operation_time < ?1 AND (title LIKE ?3 OR content_preview LIKE ?3 OR username LIKE ?3) AND {} {} LIMIT ?2,{}", get_showdown_exclusion_sql(), order_sql, ADMIN_LOG_CAPACITY_PER_PAGE),
+                false => format!("SELECT * FROM un_post WHERE operation_time < ?1 AND (title LIKE ?3 OR content_preview LIKE ?3 OR username LIKE ?3) {} LIMIT ?2,{}", order_sql, ADMIN_LOG_CAPACITY_PER_PAGE),
             };
             vault
                 .run(move |c| {
-                    c.prepare(sql.as_str())?
-                        .query_map(params![(page - 1) * ADMIN_LOG_CAPACITY_PER_PAGE], |r| {
-                            Ok(AdminLog::Post {
-                                thread_id: r.get(0)?,
-                                post_id: r.get(1)?,
-                                title: r.get(2)?,
-                                content_preview: r.get(3)?,
-                                media: r.get(4)?,
-                                username: r.get(5)?,
-                                post_time: r.get(6)?,
-                                operation: r.get(7)?,
-                                operator: r.get(8)?,
-                                operation_time: r.get(9)?,
-                            })
-                        })?
-                        .collect::<Result<Vec<AdminLog>, _>>()
+                    let mut admin_logs = c
+                        .prepare(sql.as_str())?
+                        .query_map(
+                            params![datetime, (page - 1) * ADMIN_LOG_CAPACITY_PER_PAGE, keyword],
+                            get_post_admin_log,
+                        )?
+                        .collect::<Result<Vec<AdminLog>, _>>()?;
+                    link_media_mirror(c, &mut admin_logs)?;
+                    Ok::<_, rusqlite::Error>(admin_logs)
                 })
                 .await?
         }
@@ -471,18 +2231,25 @@ async fn get_admin_logs(
             vault
                 .run(move |c| {
                     c.prepare(
-                        format!("{}{}", "SELECT * FROM un_user LIMIT ?,", ADMIN_LOG_CAPACITY_PER_PAGE).as_str(),
+                        format!(
+                            "SELECT * FROM un_user WHERE operation_time < ?1 AND username LIKE ?3 {} LIMIT ?2,{}",
+                            order_sql, ADMIN_LOG_CAPACITY_PER_PAGE
+                        )
+                        .as_str(),
+                    )?
+                    .query_map(
+                        params![datetime, (page - 1) * ADMIN_LOG_CAPACITY_PER_PAGE, keyword],
+                        |r| {
+                            Ok(AdminLog::User {
+                                avatar: r.get(0)?,
+                                username: r.get(1)?,
+                                operation: r.get(2)?,
+                                duration: r.get(3)?,
+                                operator: r.get(4)?,
+                                operation_time: r.get(5)?,
+                            })
+                        },
                     )?
-                    .query_map(params![(page - 1) * ADMIN_LOG_CAPACITY_PER_PAGE], |r| {
-                        Ok(AdminLog::User {
-                            avatar: r.get(0)?,
-                            username: r.get(1)?,
-                            operation: r.get(2)?,
-                            duration: r.get(3)?,
-                            operator: r.get(4)?,
-                            operation_time: r.get(5)?,
-                        })
-                    })?
                     .collect::<Result<Vec<AdminLog>, _>>()
                 })
                 .await?
@@ -491,17 +2258,24 @@ async fn get_admin_logs(
             vault
                 .run(move |c| {
                     c.prepare(
-                        format!("{}{}", "SELECT * FROM un_bawu LIMIT ?,", ADMIN_LOG_CAPACITY_PER_PAGE).as_str(),
+                        format!(
+                            "SELECT * FROM un_bawu WHERE operation_time < ?1 AND username LIKE ?3 {} LIMIT ?2,{}",
+                            order_sql, ADMIN_LOG_CAPACITY_PER_PAGE
+                        )
+                        .as_str(),
+                    )?
+                    .query_map(
+                        params![datetime, (page - 1) * ADMIN_LOG_CAPACITY_PER_PAGE, keyword],
+                        |r| {
+                            Ok(AdminLog::Bawu {
+                                avatar: r.get(0)?,
+                                username: r.get(1)?,
+                                operation: r.get(2)?,
+                                operator: r.get(3)?,
+                                operation_time: r.get(4)?,
+                            })
+                        },
                     )?
-                    .query_map(params![(page - 1) * ADMIN_LOG_CAPACITY_PER_PAGE], |r| {
-                        Ok(AdminLog::Bawu {
-                            avatar: r.get(0)?,
-                            username: r.get(1)?,
-                            operation: r.get(2)?,
-                            operator: r.get(3)?,
-                            operation_time: r.get(4)?,
-                        })
-                    })?
                     .collect::<Result<Vec<AdminLog>, _>>()
                 })
                 .await?
@@ -510,21 +2284,66 @@ async fn get_admin_logs(
     Ok(admin_logs)
 }
 
-#[get("/thread/<page>?<time_machine_datetime>&<search_keyword>")]
+async fn get_admin_log_count(
+    vault: &Vault,
+    category: AdminLogCategory,
+    hide_the_showdown: bool,
+    time_machine_datetime: Option<String>,
+    search_keyword: Option<String>,
+) -> Result<u32, rusqlite::Error> {
+    let datetime = get_datetime_sql_param(time_machine_datetime);
+    let keyword = get_keyword_sql_param(search_keyword);
+    let sql = match (category, hide_the_showdown) {
+        (AdminLogCategory::Post, true) => format!("SELECT COUNT(*) FROM un_post WHERE operation_time < ?1 AND (title LIKE ?2 OR content_preview LIKE ?2 OR username LIKE ?2) AND {}", get_showdown_exclusion_sql()),
+        (AdminLogCategory::Post, false) => "SELECT COUNT(*) FROM un_post WHERE operation_time < ?1 AND (title LIKE ?2 OR content_preview LIKE ?2 OR username LIKE ?2)".to_string(),
+        (AdminLogCategory::User, _) => "SELECT COUNT(*) FROM un_user WHERE operation_time < ?1 AND username LIKE ?2".to_string(),
+        (AdminLogCategory::Bawu, _) => "SELECT COUNT(*) FROM un_bawu WHERE operation_time < ?1 AND username LIKE ?2".to_string(),
+    };
+    let count = vault
+        .run(move |c| c.query_row(sql.as_str(), params![datetime, keyword], |r| r.get(0)))
+        .await?;
+    Ok(count)
+}
+
+#[allow(clippy::too_many_arguments)] // rocket query parameters
+#[get("/thread/<page>?<time_machine_datetime>&<search_keyword>&<order>&<only_good>&<min_reply_num>&<op_user_id>&<created_after>&<created_before>")]
 async fn respond_thread(
     vault: Vault,
     page: u32,
     time_machine_datetime: Option<String>,
     search_keyword: Option<String>,
+    order: Option<String>,
+    only_good: bool, // 精品
+    min_reply_num: Option<i32>,
+    op_user_id: Option<i64>,
+    created_after: Option<String>,
+    created_before: Option<String>,
 ) -> Result<Json<serde_json::Value>, Status> {
-    let search_keyword = match search_keyword {
-        Some(keyword) => Some(keyword.split_whitespace().next().unwrap().to_string()),
-        None => None,
-    }; // XXX: one keyword only?!
+    let order = match order.as_deref() {
+        None | Some("last_reply") => ThreadOrder::LastReply,
+        Some("created") => ThreadOrder::Created,
+        Some("reply_num") => ThreadOrder::ReplyNum,
+        Some("comment_activity") => ThreadOrder::CommentActivity,
+        _ => return Err(Status::NotFound),
+    };
+    let filter = ThreadFilter {
+        only_good,
+        min_reply_num,
+        op_user_id,
+        created_after,
+        created_before,
+    };
+    let search_keyword = get_search_keyword(search_keyword);
+    let search_keyword = search_keyword.map(|keyword| match emoticon::find_by_fallback(&keyword) {
+        Some(emoticon) => format!("\"{}\"", emoticon.code), // quoted, or image_emoticon1 would match image_emoticon10
+        None => keyword,
+    });
     let full_threads = get_threads(
         &vault,
         time_machine_datetime.clone(),
         search_keyword.clone(),
+        order,
+        filter,
     )
     .await
     .unwrap();
@@ -544,36 +2363,226 @@ async fn respond_thread(
         }
     };
 
-    let mut op_users: Vec<User> = Vec::new();
+    // none for reconstructed threads whose users are not in pr_user, see op_username and last_reply_username
+    let mut op_users: Vec<Option<User>> = Vec::new();
     for thread in threads {
-        op_users.push(
-            get_user_metadata(&vault, UserType::UserId, thread.op_user_id.to_string())
-                .await
-                .unwrap(),
-        );
+        op_users.push(match thread.op_user_id {
+            Some(user_id) => Some(
+                get_user_metadata(&vault, UserType::UserId, user_id.to_string())
+                    .await
+                    .unwrap(),
+            ),
+            None => None,
+        });
     }
 
-    let mut last_reply_users: Vec<User> = Vec::new();
+    let mut last_reply_users: Vec<Option<User>> = Vec::new();
     for thread in threads {
-        last_reply_users.push(
-            get_user_metadata(&vault, UserType::UserId, thread.user_id.to_string())
-                .await
-                .unwrap(),
-        );
+        last_reply_users.push(match thread.user_id {
+            Some(user_id) => Some(
+                get_user_metadata(&vault, UserType::UserId, user_id.to_string())
+                    .await
+                    .unwrap(),
+            ),
+            None => None,
+        });
     }
 
+    // none for reconstructed threads, pr_post knows nothing about them, see their reply_num instead
+    let historical_reply_counts: Option<Vec<Option<ReplyCount>>> = match &time_machine_datetime {
+        Some(datetime) => {
+            let mut reply_counts = Vec::new();
+            for thread in threads {
+                reply_counts.push(match thread.reconstructed {
+                    true => None,
+                    false => Some(
+                        get_historical_reply_count(&vault, thread.thread_id, datetime.clone())
+                            .await
+                            .unwrap(),
+                    ),
+                });
+            }
+            Some(reply_counts)
+        }
+        None => None,
+    };
+
     Ok(Json(
-        json!({"threads": threads, "op_users": op_users, "last_reply_users": last_reply_users, "max_page": max_page}),
+        json!({"threads": threads, "op_users": op_users, "last_reply_users": last_reply_users, "historical_reply_counts": historical_reply_counts, "max_page": max_page}),
     ))
 }
 
-#[get("/post/<thread_id>/<page>?<time_machine_datetime>")]
+#[get("/featured")]
+async fn respond_featured(vault: Vault) -> Result<Json<serde_json::Value>, Status> {
+    let threads = get_featured_threads(&vault).await.unwrap();
+
+    // threads are ordered by creation time, newest first, so are the years
+    let mut years: Vec<(String, Vec<FeaturedThread>)> = Vec::new();
+    for thread in threads {
+        let year = thread.created_time.chars().take(4).collect::<String>();
+        match years.last_mut() {
+            Some((last_year, year_threads)) if *last_year == year => year_threads.push(thread),
+            _ => years.push((year, vec![thread])),
+        }
+    }
+
+    Ok(Json(json!({
+        "years": years
+            .into_iter()
+            .map(|(year, threads)| json!({"year": year, "threads": threads}))
+            .collect::<Vec<serde_json::Value>>()
+    })))
+}
+
+#[get("/thread_info/<thread_id>?<time_machine_datetime>")]
+async fn respond_thread_info(
+    vault: Vault,
+    thread_id: i64,
+    time_machine_datetime: Option<String>,
+) -> Result<Json<serde_json::Value>, Status> {
+    let thread = match get_thread_metadata(&vault, thread_id).await {
+        Some(thread) => thread,
+        None => return Err(Status::NotFound),
+    };
+    let op_user = get_user_metadata(&vault, UserType::UserId, thread.user_id.to_string())
+        .await
+        .unwrap();
+    let statistics = get_thread_statistics(&vault, thread_id, time_machine_datetime)
+        .await
+        .unwrap();
+
+    Ok(Json(json!({
+        "thread_id": thread_id,
+        "title": thread.title,
+        "op_user": op_user,
+        "reply_num": thread.reply_num,
+        "is_good": thread.is_good,
+        "statistics": statistics
+    })))
+}
+
+#[get("/visibility/thread/<thread_id>?<time_machine_datetime>&<lang>")]
+async fn respond_thread_visibility(
+    vault: Vault,
+    accept_language: AcceptLanguage,
+    thread_id: i64,
+    time_machine_datetime: Option<String>,
+    lang: Option<String>,
+) -> Result<Json<serde_json::Value>, Status> {
+    let lang = match get_lang(lang, accept_language) {
+        Some(lang) => lang,
+        None => return Err(Status::NotFound),
+    };
+    let timeline =
+        get_visibility_timeline(&vault, Some(thread_id), None, time_machine_datetime, lang)
+            .await
+            .unwrap();
+    Ok(Json(json!({
+        "thread_id": thread_id,
+        "visibility": match timeline.last() {
+            Some(change) => change.visibility.as_str(),
+            None => Visibility::Visible.code(),
+        },
+        "timeline": timeline
+    })))
+}
+
+// also for comments, un_post keeps comment ids in post_id
+#[get("/visibility/post/<post_id>?<time_machine_datetime>&<lang>")]
+async fn respond_post_visibility(
+    vault: Vault,
+    accept_language: AcceptLanguage,
+    post_id: i64,
+    time_machine_datetime: Option<String>,
+    lang: Option<String>,
+) -> Result<Json<serde_json::Value>, Status> {
+    let lang = match get_lang(lang, accept_language) {
+        Some(lang) => lang,
+        None => return Err(Status::NotFound),
+    };
+    let timeline =
+        get_visibility_timeline(&vault, None, Some(post_id), time_machine_datetime, lang)
+            .await
+            .unwrap();
+    Ok(Json(json!({
+        "post_id": post_id,
+        "visibility": match timeline.last() {
+            Some(change) => change.visibility.as_str(),
+            None => Visibility::Visible.code(),
+        },
+        "timeline": timeline
+    })))
+}
+
+#[get("/discover/on_this_day?<date>")]
+async fn respond_on_this_day(
+    vault: Vault,
+    date: String,
+) -> Result<Json<serde_json::Value>, Status> {
+    // MM-DD
+    let is_valid_date = date.len() == 5
+        && date.as_bytes()[2] == b'-'
+        && date
+            .chars()
+            .enumerate()
+            .all(|(i, c)| i == 2 || c.is_ascii_digit());
+    if !is_valid_date {
+        return Err(Status::NotFound);
+    }
+
+    let threads = get_on_this_day_threads(&vault, date).await.unwrap();
+    Ok(Json(json!({ "threads": threads })))
+}
+
+#[get("/discover/random?<only_good>&<year>")]
+async fn respond_random(
+    vault: Vault,
+    only_good: bool,
+    year: Option<String>,
+) -> Result<Json<serde_json::Value>, Status> {
+    match get_random_thread(&vault, only_good, year).await {
+        Some(thread) => Ok(Json(json!(thread))),
+        None => Err(Status::NotFound),
+    }
+}
+
+#[allow(clippy::too_many_arguments)] // rocket query parameters
+#[get("/post/<thread_id>/<page>?<time_machine_datetime>&<only_op>&<only_user>&<only_user_comments>&<order>&<comment_order>&<view>&<lang>")]
 async fn respond_post(
     vault: Vault,
+    accept_language: AcceptLanguage,
     thread_id: i64,
     page: u32,
     time_machine_datetime: Option<String>,
+    only_op: bool, // 只看楼主
+    only_user: Option<i64>,
+    only_user_comments: bool,
+    order: Option<String>,
+    comment_order: Option<String>,
+    view: Option<String>,
+    lang: Option<String>,
 ) -> Result<Json<serde_json::Value>, Status> {
+    let order = match order.as_deref() {
+        None | Some("asc") => PostOrder::Floor,
+        Some("desc") => PostOrder::FloorDesc,
+        Some("most_discussed") => PostOrder::MostDiscussed,
+        _ => return Err(Status::NotFound),
+    };
+    let comment_order = match comment_order.as_deref() {
+        None | Some("asc") => CommentOrder::Time,
+        Some("desc") => CommentOrder::TimeDesc,
+        _ => return Err(Status::NotFound),
+    };
+    let view = match view.as_deref() {
+        None | Some("public") => View::Public,
+        Some("moderator") => View::Moderator,
+        _ => return Err(Status::NotFound),
+    };
+    let lang = match get_lang(lang, accept_language) {
+        Some(lang) => lang,
+        None => return Err(Status::NotFound),
+    };
+
     let admin_logs: Vec<AdminLog> =
         get_post_related_admin_logs(&vault, Some(thread_id), None, time_machine_datetime.clone())
             .await
@@ -582,17 +2591,58 @@ async fn respond_post(
     let thread = match get_thread_metadata(&vault, thread_id).await {
         Some(thread) => thread,
         None => {
-            if admin_logs.len() == 0 {
+            // ghost thread: deleted before the scraping, only known from un_post
+            let full_posts = get_ghost_posts(&vault, thread_id, time_machine_datetime)
+                .await
+                .unwrap();
+            let max_page = (full_posts.len() as f32 / POST_CAPACITY_PER_PAGE as f32).ceil() as u32;
+
+            if page > max_page {
                 return Err(Status::NotFound);
-            } else {
-                return Ok(Json(json!({"posts": [], "admin_logs": admin_logs})));
             }
+
+            let posts = match max_page {
+                1 => &full_posts[..],
+                _ if page == max_page => {
+                    &full_posts[((page - 1) * POST_CAPACITY_PER_PAGE) as usize..]
+                }
+                _ => {
+                    &full_posts[((page - 1) * POST_CAPACITY_PER_PAGE) as usize
+                        ..(page * POST_CAPACITY_PER_PAGE) as usize]
+                }
+            };
+
+            return Ok(Json(json!({
+                "title": full_posts[0].title,
+                "user_id": full_posts[0].user_id,
+                "reconstructed": true,
+                "posts": posts,
+                "admin_logs": localize_admin_logs(&admin_logs, lang),
+                "max_page": max_page
+            })));
         }
     };
 
-    let full_posts = get_posts(&vault, thread_id, time_machine_datetime.clone())
-        .await
-        .unwrap();
+    let only_user = match only_op {
+        true => Some(thread.user_id),
+        false => only_user,
+    };
+
+    let mut full_posts = get_posts(
+        &vault,
+        thread_id,
+        time_machine_datetime.clone(),
+        order,
+        view,
+    )
+    .await
+    .unwrap();
+    if let Some(user_id) = only_user {
+        full_posts.retain(|post| post.user_id == user_id);
+    }
+    for post in full_posts.iter_mut() {
+        localize_moderation(&mut post.moderation, &mut post.deletion, lang);
+    }
 
     let max_page = (full_posts.len() as f32 / POST_CAPACITY_PER_PAGE as f32).ceil() as u32;
 
@@ -612,9 +2662,21 @@ async fn respond_post(
     let mut comments: Vec<Vec<Comment>> = Vec::new();
     let mut comment_max_pages: Vec<u32> = Vec::new();
     for post in posts {
-        let full_comments = get_comments(&vault, post.post_id, time_machine_datetime.clone())
-            .await
-            .unwrap();
+        let mut full_comments = get_comments(
+            &vault,
+            post.post_id,
+            time_machine_datetime.clone(),
+            comment_order,
+            view,
+        )
+        .await
+        .unwrap();
+        if let (Some(user_id), true) = (only_user, only_user_comments) {
+            full_comments.retain(|comment| comment.user_id == user_id);
+        }
+        for comment in full_comments.iter_mut() {
+            localize_moderation(&mut comment.moderation, &mut comment.deletion, lang);
+        }
         if full_comments.len() == 0 {
             comment_max_pages.push(0);
             comments.push(Vec::new());
@@ -629,6 +2691,24 @@ async fn respond_post(
         comments.push(page_one_comments);
     }
 
+    // logs of the returned posts and comments come with them as moderation
+    // the rest stay, e.g. floors deleted before the scraping, known only from their logs
+    let linked_ids = posts
+        .iter()
+        .map(|post| post.post_id)
+        .chain(comments.iter().flatten().map(|comment| comment.comment_id))
+        .collect::<Vec<i64>>();
+    let admin_logs = admin_logs
+        .into_iter()
+        .filter(|admin_log| match admin_log {
+            AdminLog::Post {
+                post_id: Some(post_id),
+                ..
+            } => !linked_ids.contains(post_id),
+            _ => true,
+        })
+        .collect::<Vec<AdminLog>>();
+
     let mut users: Vec<User> = Vec::new();
     for post in posts {
         users.push(
@@ -651,31 +2731,204 @@ async fn respond_post(
         comment_users.push(comment_user);
     }
 
+    let historical_reply_count: Option<ReplyCount> = match &time_machine_datetime {
+        Some(datetime) => Some(
+            get_historical_reply_count(&vault, thread_id, datetime.clone())
+                .await
+                .unwrap(),
+        ),
+        None => None,
+    };
+
     Ok(Json(json!({
         "title": thread.title,
         "user_id": thread.user_id,
         "reply_num":thread.reply_num,
+        "historical_reply_count": historical_reply_count,
         "is_good": thread.is_good,
         "comments": comments,
         "comment_users": comment_users,
         "comment_max_pages": comment_max_pages,
         "users": users,
         "posts": posts,
-        "admin_logs": admin_logs,
+        "admin_logs": localize_admin_logs(&admin_logs, lang),
+        "only_user": only_user,
         "max_page": max_page
     })))
 }
 
-#[get("/comment/<post_id>/<page>?<time_machine_datetime>")]
+#[get("/post/<thread_id>/floor/<floor>?<time_machine_datetime>&<lang>")]
+async fn respond_post_floor(
+    vault: Vault,
+    accept_language: AcceptLanguage,
+    thread_id: i64,
+    floor: i32,
+    time_machine_datetime: Option<String>,
+    lang: Option<String>,
+) -> Result<Json<serde_json::Value>, Status> {
+    let lang = match get_lang(lang, accept_language) {
+        Some(lang) => lang,
+        None => return Err(Status::NotFound),
+    };
+    let posts = get_posts(
+        &vault,
+        thread_id,
+        time_machine_datetime.clone(),
+        PostOrder::Floor,
+        View::Public,
+    )
+    .await
+    .unwrap();
+
+    // nearest surviving floor, the later one wins a tie
+    let post_index = match posts
+        .iter()
+        .enumerate()
+        .min_by_key(|(_, post)| ((post.floor - floor).abs(), post.floor < floor))
+    {
+        Some((post_index, _)) => post_index,
+        None => return Err(Status::NotFound),
+    };
+    let post = &posts[post_index];
+
+    // un_post has no floor, so a deleted floor can only be matched by its post_time
+    // falling between the surviving floors around it
+    // this is a heuristic: comments share post_id in un_post, so known comments and
+    // known posts of other floors are dropped, whatever is left is only a candidate
+    let admin_logs: Vec<AdminLog> = match post.floor == floor {
+        true => Vec::new(),
+        false => {
+            let previous_time = posts
+                .iter()
+                .rev()
+                .find(|p| p.floor < floor)
+                .map(|p| p.time.clone());
+            let next_time = posts
+                .iter()
+                .find(|p| p.floor > floor)
+                .map(|p| p.time.clone());
+            get_post_related_admin_logs(&vault, Some(thread_id), None, time_machine_datetime)
+                .await
+                .unwrap()
+                .into_iter()
+                .filter(|log| match log {
+                    AdminLog::Post {
+                        post_id: Some(_),
+                        post_time,
+                        ..
+                    } => {
+                        let is_after_previous = match &previous_time {
+                            Some(previous_time) => post_time > previous_time,
+                            None => true,
+                        };
+                        let is_before_next = match &next_time {
+                            Some(next_time) => post_time < next_time,
+                            None => true,
+                        };
+                        is_after_previous && is_before_next
+                    }
+                    _ => false,
+                })
+                .collect::<Vec<AdminLog>>()
+        }
+    };
+    let candidate_ids = admin_logs
+        .iter()
+        .filter_map(|log| match log {
+            AdminLog::Post { post_id, .. } => *post_id,
+            _ => None,
+        })
+        .map(|post_id| post_id.to_string())
+        .collect::<Vec<String>>()
+        .join(",");
+    let (known_posts, known_comments) = vault
+        .run(move |c| {
+            let known_posts = c
+                .prepare(
+                    format!(
+                        "SELECT id, floor FROM pr_post WHERE id IN ({})",
+                        candidate_ids
+                    )
+                    .as_str(),
+                )?
+                .query_map(params![], |r| {
+                    Ok((r.get::<usize, i64>(0)?, r.get::<usize, i32>(1)?))
+                })?
+                .collect::<Result<BTreeMap<i64, i32>, _>>()?;
+            let known_comments = c
+                .prepare(
+                    format!("SELECT id FROM pr_comment WHERE id IN ({})", candidate_ids).as_str(),
+                )?
+                .query_map(params![], |r| r.get::<usize, i64>(0))?
+                .collect::<Result<Vec<i64>, _>>()?;
+            Ok::<_, rusqlite::Error>((known_posts, known_comments))
+        })
+        .await
+        .unwrap();
+    let admin_logs = admin_logs
+        .into_iter()
+        .filter(|log| match log {
+            AdminLog::Post {
+                post_id: Some(post_id),
+                ..
+            } => {
+                !known_comments.contains(post_id)
+                    && match known_posts.get(post_id) {
+                        Some(known_floor) => *known_floor == floor, // hidden by the time machine, still the floor asked for
+                        None => true,
+                    }
+            }
+            _ => false,
+        })
+        .collect::<Vec<AdminLog>>();
+
+    Ok(Json(json!({
+        "thread_id": thread_id,
+        "floor": floor,
+        "found_floor": post.floor,
+        "post_id": post.post_id,
+        "page": post_index as u32 / POST_CAPACITY_PER_PAGE + 1,
+        "admin_logs": localize_admin_logs(&admin_logs, lang)
+    })))
+}
+
+#[allow(clippy::too_many_arguments)] // rocket query parameters
+#[get("/comment/<post_id>/<page>?<time_machine_datetime>&<only_user>&<order>&<view>&<lang>")]
 async fn respond_comment(
     vault: Vault,
+    accept_language: AcceptLanguage,
     post_id: i64,
     page: u32,
     time_machine_datetime: Option<String>,
+    only_user: Option<i64>,
+    order: Option<String>,
+    view: Option<String>,
+    lang: Option<String>,
 ) -> Result<Json<serde_json::Value>, Status> {
-    let full_comments = get_comments(&vault, post_id, time_machine_datetime.clone())
-        .await
-        .unwrap();
+    let order = match order.as_deref() {
+        None | Some("asc") => CommentOrder::Time,
+        Some("desc") => CommentOrder::TimeDesc,
+        _ => return Err(Status::NotFound),
+    };
+    let view = match view.as_deref() {
+        None | Some("public") => View::Public,
+        Some("moderator") => View::Moderator,
+        _ => return Err(Status::NotFound),
+    };
+    let lang = match get_lang(lang, accept_language) {
+        Some(lang) => lang,
+        None => return Err(Status::NotFound),
+    };
+    let mut full_comments =
+        get_comments(&vault, post_id, time_machine_datetime.clone(), order, view)
+            .await
+            .unwrap();
+    if let Some(user_id) = only_user {
+        full_comments.retain(|comment| comment.user_id == user_id);
+    }
+    for comment in full_comments.iter_mut() {
+        localize_moderation(&mut comment.moderation, &mut comment.deletion, lang);
+    }
 
     let max_page = (full_comments.len() as f32 / COMMENT_CAPACITY_PER_PAGE as f32).ceil() as u32;
 
@@ -703,14 +2956,28 @@ async fn respond_comment(
         );
     }
 
+    // same as respond_post, logs of the returned comments come with them as moderation
     let admin_logs: Vec<AdminLog> =
         get_post_related_admin_logs(&vault, None, Some(post_id), time_machine_datetime)
             .await
-            .unwrap();
+            .unwrap()
+            .into_iter()
+            .filter(|admin_log| match admin_log {
+                AdminLog::Post {
+                    post_id: Some(log_post_id),
+                    ..
+                } => !comments
+                    .iter()
+                    .any(|comment| comment.comment_id == *log_post_id),
+                _ => true,
+            })
+            .collect();
 
-    Ok(Json(
-        json!({"comments": comments, "users": users, "admin_logs": admin_logs}),
-    ))
+    Ok(Json(json!({
+        "comments": comments,
+        "users": users,
+        "admin_logs": localize_admin_logs(&admin_logs, lang)
+    })))
 }
 
 #[get("/user/<user_type>/<user_clue>/<page>?<time_machine_datetime>")]
@@ -765,37 +3032,502 @@ async fn respond_user(
     }
 }
 
-#[get("/admin_log/<category>/<page>?<hide_the_showdown>")]
+#[get(
+    "/user/<user_type>/<user_clue>/mentions?<page>&<time_machine_datetime>",
+    rank = 1
+)]
+async fn respond_user_mentions(
+    vault: Vault,
+    user_type: String,
+    user_clue: String,
+    page: Option<u32>,
+    time_machine_datetime: Option<String>,
+) -> Result<Json<serde_json::Value>, Status> {
+    let page = page.unwrap_or(1);
+    let user_type = match user_type.as_str() {
+        "user_id" => UserType::UserId,
+        "username" => UserType::Username,
+        "nickname" => UserType::Nickname,
+        "avatar" => UserType::Avatar,
+        _ => return Err(Status::NotFound),
+    };
+    let user = match get_user_metadata(&vault, user_type, user_clue).await {
+        Some(user) => user,
+        None => return Err(Status::NotFound),
+    };
+
+    let full_mentions = get_mentions(&vault, &user, time_machine_datetime)
+        .await
+        .unwrap();
+
+    let max_page =
+        (full_mentions.len() as f32 / USER_RECORD_CAPACITY_PER_PAGE as f32).ceil() as u32;
+
+    if page > max_page {
+        return Err(Status::NotFound);
+    }
+
+    let mentions = match max_page {
+        1 => &full_mentions[..],
+        _ if page == max_page => {
+            &full_mentions[((page - 1) * USER_RECORD_CAPACITY_PER_PAGE) as usize..]
+        }
+        _ => {
+            &full_mentions[((page - 1) * USER_RECORD_CAPACITY_PER_PAGE) as usize
+                ..(page * USER_RECORD_CAPACITY_PER_PAGE) as usize]
+        }
+    };
+
+    let mut users: Vec<User> = Vec::new();
+    for mention in mentions {
+        users.push(
+            get_user_metadata(&vault, UserType::UserId, mention.user_id.to_string())
+                .await
+                .unwrap(),
+        );
+    }
+
+    Ok(Json(json!({
+        "user_id": user.user_id,
+        "username": user.username,
+        "nickname": user.nickname,
+        "avatar": user.avatar,
+        "mentions": mentions,
+        "users": users,
+        "max_page": max_page
+    })))
+}
+
+#[get(
+    "/user/<user_type>/<user_clue>/signatures?<time_machine_datetime>",
+    rank = 1
+)]
+async fn respond_user_signatures(
+    vault: Vault,
+    user_type: String,
+    user_clue: String,
+    time_machine_datetime: Option<String>,
+) -> Result<Json<serde_json::Value>, Status> {
+    let user_type = match user_type.as_str() {
+        "user_id" => UserType::UserId,
+        "username" => UserType::Username,
+        "nickname" => UserType::Nickname,
+        "avatar" => UserType::Avatar,
+        _ => return Err(Status::NotFound),
+    };
+    match get_user_metadata(&vault, user_type, user_clue).await {
+        Some(user) => {
+            let signatures = get_signature_history(&vault, user.user_id, time_machine_datetime)
+                .await
+                .unwrap();
+            Ok(Json(json!({
+                "user_id": user.user_id,
+                "username": user.username,
+                "nickname": user.nickname,
+                "avatar": user.avatar,
+                "signatures": signatures
+            })))
+        }
+        None => Err(Status::NotFound),
+    }
+}
+
+#[get("/mention_graph?<time_machine_datetime>")]
+async fn respond_mention_graph(
+    vault: Vault,
+    time_machine_datetime: Option<String>,
+) -> Result<Json<serde_json::Value>, Status> {
+    let edges = get_mention_graph(&vault, time_machine_datetime)
+        .await
+        .unwrap();
+    Ok(Json(json!({ "edges": edges })))
+}
+
+#[allow(clippy::too_many_arguments)] // rocket query parameters
+#[get("/admin_log/<category>/<page>?<hide_the_showdown>&<lang>&<time_machine_datetime>&<order>")]
 async fn respond_admin_log(
     vault: Vault,
+    accept_language: AcceptLanguage,
     category: String,
     page: u32,
     hide_the_showdown: bool,
+    lang: Option<String>,
+    time_machine_datetime: Option<String>,
+    order: Option<String>,
 ) -> Result<Json<serde_json::Value>, Status> {
+    // newest first, like the tieba backstage
+    let order = match order.as_deref() {
+        None | Some("desc") => AdminLogOrder::TimeDesc,
+        Some("asc") => AdminLogOrder::Time,
+        _ => return Err(Status::NotFound),
+    };
+    let lang = match get_lang(lang, accept_language) {
+        Some(lang) => lang,
+        None => return Err(Status::NotFound),
+    };
     match category.as_str() {
         "post" => {
-            let admin_logs =
-                get_admin_logs(&vault, AdminLogCategory::Post, page, hide_the_showdown)
-                    .await
-                    .unwrap();
-            Ok(Json(json!(admin_logs)))
+            let admin_logs = get_admin_logs(
+                &vault,
+                AdminLogCategory::Post,
+                page,
+                hide_the_showdown,
+                time_machine_datetime,
+                order,
+                None,
+            )
+            .await
+            .unwrap();
+            Ok(Json(json!(localize_admin_logs(&admin_logs, lang))))
         }
         "user" => {
-            let admin_logs =
-                get_admin_logs(&vault, AdminLogCategory::User, page, hide_the_showdown)
-                    .await
-                    .unwrap();
-            Ok(Json(json!(admin_logs)))
+            let admin_logs = get_admin_logs(
+                &vault,
+                AdminLogCategory::User,
+                page,
+                hide_the_showdown,
+                time_machine_datetime,
+                order,
+                None,
+            )
+            .await
+            .unwrap();
+            Ok(Json(json!(localize_admin_logs(&admin_logs, lang))))
         }
         "bawu" => {
-            let admin_logs =
-                get_admin_logs(&vault, AdminLogCategory::Bawu, page, hide_the_showdown)
+            let admin_logs = get_admin_logs(
+                &vault,
+                AdminLogCategory::Bawu,
+                page,
+                hide_the_showdown,
+                time_machine_datetime,
+                order,
+                None,
+            )
+            .await
+            .unwrap();
+            Ok(Json(json!(localize_admin_logs(&admin_logs, lang))))
+        }
+        _ => Err(Status::NotFound),
+    }
+}
+
+#[allow(clippy::too_many_arguments)] // rocket query parameters
+#[get("/admin_log_search/<category>/<page>?<search_keyword>&<hide_the_showdown>&<time_machine_datetime>&<order>&<lang>")]
+async fn respond_admin_log_search(
+    vault: Vault,
+    accept_language: AcceptLanguage,
+    category: String,
+    page: u32,
+    search_keyword: String,
+    hide_the_showdown: bool,
+    time_machine_datetime: Option<String>,
+    order: Option<String>,
+    lang: Option<String>,
+) -> Result<Json<serde_json::Value>, Status> {
+    let category = match category.as_str() {
+        "post" => AdminLogCategory::Post,
+        "user" => AdminLogCategory::User,
+        "bawu" => AdminLogCategory::Bawu,
+        _ => return Err(Status::NotFound),
+    };
+    let order = match order.as_deref() {
+        None | Some("desc") => AdminLogOrder::TimeDesc,
+        Some("asc") => AdminLogOrder::Time,
+        _ => return Err(Status::NotFound),
+    };
+    let lang = match get_lang(lang, accept_language) {
+        Some(lang) => lang,
+        None => return Err(Status::NotFound),
+    };
+    let keyword = match get_search_keyword(Some(search_keyword)) {
+        Some(keyword) => keyword,
+        None => return Err(Status::NotFound),
+    };
+
+    let count = get_admin_log_count(
+        &vault,
+        category,
+        hide_the_showdown,
+        time_machine_datetime.clone(),
+        Some(keyword.clone()),
+    )
+    .await
+    .unwrap();
+    let max_page = (count as f32 / ADMIN_LOG_CAPACITY_PER_PAGE as f32).ceil() as u32;
+
+    if page > max_page {
+        return Err(Status::NotFound);
+    }
+
+    let admin_logs = get_admin_logs(
+        &vault,
+        category,
+        page,
+        hide_the_showdown,
+        time_machine_datetime,
+        order,
+        Some(keyword.clone()),
+    )
+    .await
+    .unwrap();
+
+    let highlights = admin_logs
+        .iter()
+        .map(|admin_log| match admin_log {
+            AdminLog::Post {
+                title,
+                content_preview,
+                username,
+                ..
+            } => json!({
+                "title": get_highlights(title, &keyword),
+                "content_preview": get_highlights(content_preview, &keyword),
+                "username": get_highlights(username, &keyword)
+            }),
+            AdminLog::User { username, .. } | AdminLog::Bawu { username, .. } => {
+                json!({ "username": get_highlights(username, &keyword) })
+            }
+        })
+        .collect::<Vec<serde_json::Value>>();
+
+    Ok(Json(json!({
+        "admin_logs": localize_admin_logs(&admin_logs, lang),
+        "highlights": highlights,
+        "max_page": max_page
+    })))
+}
+
+#[get("/moderator_report?<time_machine_datetime>")]
+async fn respond_moderator_report(
+    vault: Vault,
+    time_machine_datetime: Option<String>,
+) -> Json<serde_json::Value> {
+    let activities = get_moderator_activities(&vault, time_machine_datetime)
+        .await
+        .unwrap();
+    Json(json!({ "moderators": activities }))
+}
+
+#[get("/link/<page>?<domain>&<dead_only>")]
+async fn respond_link(
+    vault: Vault,
+    page: u32,
+    domain: Option<String>,
+    dead_only: bool,
+) -> Result<Json<serde_json::Value>, Status> {
+    let full_domains = get_outbound_links(&vault)
+        .await
+        .unwrap()
+        .into_iter()
+        .filter(|d| match &domain {
+            Some(domain) => d.domain == domain.to_lowercase(),
+            None => true,
+        })
+        .filter_map(|mut d| match dead_only {
+            true => {
+                d.links.retain(|link| link.dead == Some(true));
+                d.count = d.links.iter().map(|link| link.count).sum();
+                if d.links.is_empty() {
+                    None
+                } else {
+                    Some(d)
+                }
+            }
+            false => Some(d),
+        })
+        .collect::<Vec<LinkDomain>>();
+
+    let max_page = (full_domains.len() as f32 / LINK_DOMAIN_CAPACITY_PER_PAGE as f32).ceil() as u32;
+
+    if page > max_page {
+        return Err(Status::NotFound);
+    }
+
+    let domains = match max_page {
+        1 => &full_domains[..],
+        _ if page == max_page => {
+            &full_domains[((page - 1) * LINK_DOMAIN_CAPACITY_PER_PAGE) as usize..]
+        }
+        _ => {
+            &full_domains[((page - 1) * LINK_DOMAIN_CAPACITY_PER_PAGE) as usize
+                ..(page * LINK_DOMAIN_CAPACITY_PER_PAGE) as usize]
+        }
+    };
+
+    Ok(Json(json!({"domains": domains, "max_page": max_page})))
+}
+
+#[get("/emoticon?<time_machine_datetime>")]
+async fn respond_emoticon(
+    vault: Vault,
+    time_machine_datetime: Option<String>,
+) -> Result<Json<serde_json::Value>, Status> {
+    let emoticons = get_emoticon_usages(&vault, time_machine_datetime)
+        .await
+        .unwrap();
+    Ok(Json(json!({ "emoticons": emoticons })))
+}
+
+#[get("/export/<thread_id>?<time_machine_datetime>")]
+async fn respond_export(
+    vault: Vault,
+    thread_id: i64,
+    time_machine_datetime: Option<String>,
+) -> Result<String, Status> {
+    let thread = match get_thread_metadata(&vault, thread_id).await {
+        Some(thread) => thread,
+        None => return Err(Status::NotFound),
+    };
+
+    let posts = get_posts(
+        &vault,
+        thread_id,
+        time_machine_datetime.clone(),
+        PostOrder::Floor,
+        View::Public,
+    )
+    .await
+    .unwrap();
+
+    let mut text = format!("{}\n", thread.title);
+    for post in posts {
+        let user = get_user_metadata(&vault, UserType::UserId, post.user_id.to_string())
+            .await
+            .unwrap();
+        text.push_str(&format!(
+            "\n#{} {} {}\n{}\n",
+            post.floor,
+            user.nickname,
+            post.time,
+            content::to_plain_text(&post.content)
+        ));
+        for comment in get_comments(
+            &vault,
+            post.post_id,
+            time_machine_datetime.clone(),
+            CommentOrder::Time,
+            View::Public,
+        )
+        .await
+        .unwrap()
+        {
+            let comment_user =
+                get_user_metadata(&vault, UserType::UserId, comment.user_id.to_string())
                     .await
                     .unwrap();
-            Ok(Json(json!(admin_logs)))
+            text.push_str(&format!(
+                "    {} {}: {}\n",
+                comment_user.nickname,
+                comment.time,
+                content::to_plain_text(&comment.content)
+            ));
         }
-        _ => Err(Status::NotFound),
     }
+    Ok(text)
+}
+
+#[get("/tail?<user_id>&<thread_id>&<time_machine_datetime>")]
+async fn respond_tail(
+    vault: Vault,
+    user_id: Option<i64>,
+    thread_id: Option<i64>,
+    time_machine_datetime: Option<String>,
+) -> Result<Json<serde_json::Value>, Status> {
+    let (months, clients) = get_tail_statistics(&vault, user_id, thread_id, time_machine_datetime)
+        .await
+        .unwrap();
+    Ok(Json(json!({"months": months, "clients": clients})))
+}
+
+// page numbers are computed over the same lists respond_post and respond_comment paginate
+#[get("/locate/post/<post_id>?<time_machine_datetime>")]
+async fn respond_locate_post(
+    vault: Vault,
+    post_id: i64,
+    time_machine_datetime: Option<String>,
+) -> Result<Json<serde_json::Value>, Status> {
+    let thread_id = match get_post_thread_id(&vault, post_id).await {
+        Some(thread_id) => thread_id,
+        None => return Err(Status::NotFound),
+    };
+
+    let posts = get_posts(
+        &vault,
+        thread_id,
+        time_machine_datetime,
+        PostOrder::Floor,
+        View::Public,
+    )
+    .await
+    .unwrap();
+    let post_index = match posts.iter().position(|post| post.post_id == post_id) {
+        Some(post_index) => post_index as u32,
+        None => return Err(Status::NotFound), // not posted yet at time_machine_datetime
+    };
+
+    Ok(Json(json!({
+        "thread_id": thread_id,
+        "post_id": post_id,
+        "floor": posts[post_index as usize].floor,
+        "page": post_index / POST_CAPACITY_PER_PAGE + 1
+    })))
+}
+
+#[get("/locate/comment/<comment_id>?<time_machine_datetime>")]
+async fn respond_locate_comment(
+    vault: Vault,
+    comment_id: i64,
+    time_machine_datetime: Option<String>,
+) -> Result<Json<serde_json::Value>, Status> {
+    let post_id = match get_comment_post_id(&vault, comment_id).await {
+        Some(post_id) => post_id,
+        None => return Err(Status::NotFound),
+    };
+    let thread_id = match get_post_thread_id(&vault, post_id).await {
+        Some(thread_id) => thread_id,
+        None => return Err(Status::NotFound),
+    };
+
+    let posts = get_posts(
+        &vault,
+        thread_id,
+        time_machine_datetime.clone(),
+        PostOrder::Floor,
+        View::Public,
+    )
+    .await
+    .unwrap();
+    let post_index = match posts.iter().position(|post| post.post_id == post_id) {
+        Some(post_index) => post_index as u32,
+        None => return Err(Status::NotFound),
+    };
+
+    let comments = get_comments(
+        &vault,
+        post_id,
+        time_machine_datetime,
+        CommentOrder::Time,
+        View::Public,
+    )
+    .await
+    .unwrap();
+    let comment_index = match comments
+        .iter()
+        .position(|comment| comment.comment_id == comment_id)
+    {
+        Some(comment_index) => comment_index as u32,
+        None => return Err(Status::NotFound),
+    };
+
+    Ok(Json(json!({
+        "thread_id": thread_id,
+        "post_id": post_id,
+        "floor": posts[post_index as usize].floor,
+        "page": post_index / POST_CAPACITY_PER_PAGE + 1,
+        "comment_id": comment_id,
+        "comment_page": comment_index / COMMENT_CAPACITY_PER_PAGE + 1
+    })))
 }
 
 #[get("/")]
@@ -813,24 +3545,59 @@ impl Fairing for CustomHeaders {
         }
     }
 
-    async fn on_response<'r>(&self, _request: &'r Request<'_>, response: &mut Response<'r>) {
+    async fn on_response<'r>(&self, request: &'r Request<'_>, response: &mut Response<'r>) {
         response.set_header(Header::new("Access-Control-Allow-Origin", "*"));
         response.set_header(Header::new("Access-Control-Allow-Methods", "GET"));
-        response.set_header(Header::new("Cache-Control", "max-age=14400"));
-    }
-}
-
-#[launch]
-fn rocket() -> _ {
-    rocket::build().attach(Vault::fairing()).attach(CustomHeaders).mount(
-        "/",
-        routes![
-            respond_thread,
-            respond_post,
-            respond_comment,
-            respond_user,
-            respond_admin_log,
-            rickroll
-        ],
-    )
+        match request.uri().path().as_str() {
+            "/discover/random" => response.set_header(Header::new("Cache-Control", "no-store")),
+            _ => response.set_header(Header::new("Cache-Control", "max-age=14400")),
+        };
+    }
+}
+
+#[rocket::main]
+async fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    if args.get(1).map(String::as_str) == Some("link-check") {
+        let vault_path: String = rocket::Config::figment()
+            .extract_inner("databases.vault.url")
+            .unwrap_or_else(|_| "vault.db".to_string());
+        std::process::exit(link_check::run(&vault_path, &args[2..]));
+    }
+    let _ = rocket().launch().await; // a dropped rocket::Error reports itself, same as #[launch]
+}
+
+fn rocket() -> rocket::Rocket<rocket::Build> {
+    rocket::build()
+        .attach(Vault::fairing())
+        .attach(CustomHeaders)
+        .mount(
+            "/",
+            routes![
+                respond_thread,
+                respond_featured,
+                respond_thread_info,
+                respond_thread_visibility,
+                respond_post_visibility,
+                respond_on_this_day,
+                respond_random,
+                respond_post,
+                respond_post_floor,
+                respond_comment,
+                respond_user,
+                respond_user_mentions,
+                respond_user_signatures,
+                respond_mention_graph,
+                respond_admin_log,
+                respond_admin_log_search,
+                respond_moderator_report,
+                respond_link,
+                respond_emoticon,
+                respond_export,
+                respond_tail,
+                respond_locate_post,
+                respond_locate_comment,
+                rickroll
+            ],
+        )
 }
@@ -0,0 +1,104 @@
+// Parses un_post.media, which comes in a few shapes depending on the scraping round:
+// a JSON array of urls or of objects like {"url": ..., "width": ..., "height": ...}, or urls separated by commas or whitespace
+
+pub enum MediaKind {
+    Image,
+    Video,
+}
+
+impl MediaKind {
+    pub fn code(&self) -> &'static str {
+        match self {
+            MediaKind::Image => "image",
+            MediaKind::Video => "video",
+        }
+    }
+}
+
+pub struct Media {
+    pub kind: MediaKind,
+    pub url: String,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+}
+
+fn get_kind(url: &str) -> MediaKind {
+    let path = url
+        .split(['?', '#'])
+        .next()
+        .unwrap_or_default()
+        .to_lowercase();
+    if [".mp4", ".flv", ".mov", ".m3u8", ".swf"]
+        .iter()
+        .any(|extension| path.ends_with(extension))
+        || path.contains("/video/")
+    {
+        MediaKind::Video
+    } else {
+        MediaKind::Image
+    }
+}
+
+// baidu image urls carry the size as a processing parameter, e.g. .../forum/w%3D580/sign=.../xxx.jpg
+fn get_url_dimension(url: &str, key: &str) -> Option<u32> {
+    let url = url.replace("%3D", "=").replace("%3d", "=");
+    url.split(['/', '?', '&', ',', ';'])
+        .find_map(|part| part.strip_prefix(key)?.strip_prefix('='))
+        .and_then(|value| {
+            value
+                .chars()
+                .take_while(|c| c.is_ascii_digit())
+                .collect::<String>()
+                .parse()
+                .ok()
+        })
+}
+
+fn get_media(url: &str, width: Option<u32>, height: Option<u32>, kind: Option<&str>) -> Media {
+    Media {
+        kind: match kind {
+            Some("video") => MediaKind::Video,
+            Some("image") => MediaKind::Image,
+            _ => get_kind(url),
+        },
+        url: url.to_string(),
+        width: width.or_else(|| get_url_dimension(url, "w")),
+        height: height.or_else(|| get_url_dimension(url, "h")),
+    }
+}
+
+pub fn parse(media: Option<&str>) -> Vec<Media> {
+    let media = media.unwrap_or_default().trim();
+    if media.is_empty() {
+        return Vec::new();
+    }
+    if let Ok(serde_json::Value::Array(entries)) = serde_json::from_str(media) {
+        return entries
+            .iter()
+            .filter_map(|entry| match entry {
+                serde_json::Value::String(url) => Some(get_media(url, None, None, None)),
+                other => {
+                    let url = ["url", "src", "original_src", "big_src"]
+                        .iter()
+                        .find_map(|key| other[key].as_str())?;
+                    let get_dimension = |keys: [&str; 2]| {
+                        keys.iter()
+                            .find_map(|key| other[key].as_u64())
+                            .map(|dimension| dimension as u32)
+                    };
+                    Some(get_media(
+                        url,
+                        get_dimension(["width", "w"]),
+                        get_dimension(["height", "h"]),
+                        other["type"].as_str(),
+                    ))
+                }
+            })
+            .collect();
+    }
+    media
+        .split(|c: char| c == ',' || c == ';' || c == '|' || c.is_whitespace())
+        .filter(|url| url.starts_with("http://") || url.starts_with("https://"))
+        .map(|url| get_media(url, None, None, None))
+        .collect()
+}
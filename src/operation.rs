@@ -0,0 +1,144 @@
+// Normalizes the raw operations of un_post, un_user and un_bawu into stable codes with localized labels
+// Unknown operations fall back to Other, the raw string is always kept in AdminLog.operation
+
+#[derive(Clone, Copy)]
+pub enum Lang {
+    ZhCn,
+    ZhTw,
+    En,
+}
+
+impl Lang {
+    pub fn parse(lang: &str) -> Option<Lang> {
+        let lang = lang.trim().to_lowercase();
+        if lang.starts_with("zh-tw") || lang.starts_with("zh-hk") || lang.starts_with("zh-hant") {
+            Some(Lang::ZhTw)
+        } else if lang.starts_with("zh") {
+            Some(Lang::ZhCn)
+        } else if lang.starts_with("en") {
+            Some(Lang::En)
+        } else {
+            None
+        }
+    }
+
+    // e.g. "en-US,en;q=0.9,zh-CN;q=0.8", browsers already list them by preference
+    pub fn from_accept_language(header: &str) -> Option<Lang> {
+        header
+            .split(',')
+            .filter_map(|entry| entry.split(';').next())
+            .find_map(Lang::parse)
+    }
+}
+
+pub enum Operation {
+    DeletePost,
+    RestorePost,
+    AddGood,
+    RemoveGood,
+    AddTop,
+    RemoveTop,
+    Ban,
+    Unban,
+    AddBlacklist,
+    RemoveBlacklist,
+    AppointBawu,
+    DismissBawu,
+    Other,
+}
+
+impl Operation {
+    pub fn parse(operation: &str) -> Operation {
+        match operation.trim() {
+            "删贴" | "删帖" => Operation::DeletePost,
+            "恢复删贴" | "恢复删帖" | "恢复" => Operation::RestorePost,
+            "加精" => Operation::AddGood,
+            "取消加精" => Operation::RemoveGood,
+            "置顶" => Operation::AddTop,
+            "取消置顶" => Operation::RemoveTop,
+            "封禁" => Operation::Ban,
+            "解封" | "解除封禁" | "取消封禁" => Operation::Unban,
+            "拉黑" | "加入黑名单" => Operation::AddBlacklist,
+            "取消拉黑" | "移出黑名单" | "解除黑名单" => Operation::RemoveBlacklist,
+            operation if operation.starts_with("任命") || operation.starts_with("添加") => {
+                Operation::AppointBawu
+            }
+            operation if operation.starts_with("撤销") || operation.starts_with("卸任") => {
+                Operation::DismissBawu
+            }
+            _ => Operation::Other,
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            Operation::DeletePost => "delete_post",
+            Operation::RestorePost => "restore_post",
+            Operation::AddGood => "add_good",
+            Operation::RemoveGood => "remove_good",
+            Operation::AddTop => "add_top",
+            Operation::RemoveTop => "remove_top",
+            Operation::Ban => "ban",
+            Operation::Unban => "unban",
+            Operation::AddBlacklist => "add_blacklist",
+            Operation::RemoveBlacklist => "remove_blacklist",
+            Operation::AppointBawu => "appoint_bawu",
+            Operation::DismissBawu => "dismiss_bawu",
+            Operation::Other => "other",
+        }
+    }
+
+    pub fn label(&self, lang: Lang) -> &'static str {
+        let (zh_cn, zh_tw, en) = match self {
+            Operation::DeletePost => ("删贴", "刪帖", "Delete post"),
+            Operation::RestorePost => ("恢复删贴", "恢復刪帖", "Restore post"),
+            Operation::AddGood => ("加精", "加精", "Mark as featured"),
+            Operation::RemoveGood => ("取消加精", "取消加精", "Unmark as featured"),
+            Operation::AddTop => ("置顶", "置頂", "Pin"),
+            Operation::RemoveTop => ("取消置顶", "取消置頂", "Unpin"),
+            Operation::Ban => ("封禁", "封鎖", "Ban"),
+            Operation::Unban => ("解封", "解除封鎖", "Unban"),
+            Operation::AddBlacklist => ("拉黑", "加入黑名單", "Blacklist"),
+            Operation::RemoveBlacklist => ("取消拉黑", "移出黑名單", "Remove from blacklist"),
+            Operation::AppointBawu => ("任命吧务", "任命吧務", "Appoint moderator"),
+            Operation::DismissBawu => ("撤销吧务", "撤銷吧務", "Dismiss moderator"),
+            Operation::Other => ("其他", "其他", "Other"),
+        };
+        match lang {
+            Lang::ZhCn => zh_cn,
+            Lang::ZhTw => zh_tw,
+            Lang::En => en,
+        }
+    }
+}
+
+// un_user.duration looks like "1天" or "10天", sometimes with hours, e.g. "1天12小时"
+// none for 永久 or anything unrecognized
+pub fn parse_duration(duration: &str) -> Option<u64> {
+    let mut seconds = 0;
+    let mut number = String::new();
+    let mut found = false;
+    for c in duration.trim().chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+        let unit = match c {
+            '天' | '日' => 86400,
+            '时' => 3600,
+            '分' => 60,
+            '秒' => 1,
+            _ => continue, // 小 of 小时, 钟 of 分钟, spaces
+        };
+        if number.is_empty() {
+            return None;
+        }
+        seconds += number.parse::<u64>().ok()? * unit;
+        number.clear();
+        found = true;
+    }
+    match found && number.is_empty() {
+        true => Some(seconds),
+        false => None,
+    }
+}
@@ -0,0 +1,69 @@
+// Normalizes pr_post.tail, e.g. "来自Android客户端", into a platform and a client name
+// No tail at all means the floor was posted from the desktop web
+
+pub enum Platform {
+    Android,
+    Ios,
+    WindowsPhone,
+    Desktop,
+    Symbian,
+    MobileWeb,
+    Web,
+    Other,
+}
+
+impl Platform {
+    pub fn code(&self) -> &'static str {
+        match self {
+            Platform::Android => "android",
+            Platform::Ios => "ios",
+            Platform::WindowsPhone => "windows_phone",
+            Platform::Desktop => "desktop",
+            Platform::Symbian => "symbian",
+            Platform::MobileWeb => "mobile_web",
+            Platform::Web => "web",
+            Platform::Other => "other",
+        }
+    }
+}
+
+pub fn classify(tail: Option<&str>) -> (Platform, String) {
+    let tail = tail.unwrap_or_default().trim();
+    if tail.is_empty() {
+        return (Platform::Web, "web".to_string());
+    }
+    let client = tail
+        .trim_start_matches("来自")
+        .trim_end_matches("客户端")
+        .trim();
+    let lowercase = client.to_lowercase();
+    let platform = if lowercase.contains("android") || lowercase.contains("安卓") {
+        Platform::Android
+    } else if ["iphone", "ipad", "ipod", "ios"]
+        .iter()
+        .any(|k| lowercase.contains(k))
+    {
+        Platform::Ios
+    } else if lowercase.contains("windows phone") || lowercase.starts_with("wp") {
+        Platform::WindowsPhone
+    } else if ["windows", "win8", "win10", "mac", "uwp"]
+        .iter()
+        .any(|k| lowercase.contains(k))
+    {
+        // desktop clients, e.g. 来自Windows 8客户端
+        Platform::Desktop
+    } else if ["symbian", "塞班", "s60"]
+        .iter()
+        .any(|k| lowercase.contains(k))
+    {
+        Platform::Symbian
+    } else if ["手机贴吧", "掌上百度", "3g", "wap", "触屏"]
+        .iter()
+        .any(|k| lowercase.contains(k))
+    {
+        Platform::MobileWeb
+    } else {
+        Platform::Other
+    };
+    (platform, client.to_string())
+}
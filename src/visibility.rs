@@ -0,0 +1,125 @@
+// Deletion state machine over un_post
+// Every thread, post and comment starts out visible, 删贴 hides it and 恢复删贴 brings it back
+// Other operations, e.g. 加精 or 置顶, leave the visibility alone
+// What counts as 删贴 or 恢复删贴 is up to operation::Operation::parse
+
+use std::collections::BTreeMap;
+
+use crate::operation::Operation;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Visibility {
+    Visible,
+    Deleted,
+}
+
+impl Visibility {
+    pub fn code(&self) -> &'static str {
+        match self {
+            Visibility::Visible => "visible",
+            Visibility::Deleted => "deleted",
+        }
+    }
+
+    pub fn apply(self, operation: &str) -> Visibility {
+        match Operation::parse(operation) {
+            Operation::DeletePost => Visibility::Deleted,
+            Operation::RestorePost => Visibility::Visible,
+            _ => self,
+        }
+    }
+}
+
+// logs are (key, operation, log) in operation_time order, rows sharing a time are taken as given
+// returns the log that deleted each key still deleted at the end, the first 删贴 of a run wins
+pub fn replay<T>(logs: impl IntoIterator<Item = (i64, String, T)>) -> BTreeMap<i64, T> {
+    let mut deletion_logs: BTreeMap<i64, T> = BTreeMap::new();
+    for (key, operation, log) in logs {
+        let visibility = match deletion_logs.contains_key(&key) {
+            true => Visibility::Deleted,
+            false => Visibility::Visible,
+        };
+        match (visibility, visibility.apply(&operation)) {
+            (Visibility::Visible, Visibility::Deleted) => {
+                deletion_logs.insert(key, log);
+            }
+            (Visibility::Deleted, Visibility::Visible) => {
+                deletion_logs.remove(&key);
+            }
+            _ => (),
+        }
+    }
+    deletion_logs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn replay_logs(logs: &[(i64, &str, u32)]) -> BTreeMap<i64, u32> {
+        replay(
+            logs.iter()
+                .map(|(key, operation, log)| (*key, operation.to_string(), *log)),
+        )
+    }
+
+    #[test]
+    fn apply_only_changes_on_deletion_operations() {
+        assert!(Visibility::Visible.apply("删贴") == Visibility::Deleted);
+        assert!(Visibility::Visible.apply("删帖") == Visibility::Deleted);
+        assert!(Visibility::Deleted.apply("恢复删贴") == Visibility::Visible);
+        assert!(Visibility::Deleted.apply("恢复") == Visibility::Visible);
+        for operation in ["加精", "取消加精", "置顶", "取消置顶", "封禁", "未知"] {
+            assert!(Visibility::Visible.apply(operation) == Visibility::Visible);
+            assert!(Visibility::Deleted.apply(operation) == Visibility::Deleted);
+        }
+    }
+
+    #[test]
+    fn delete_restore_delete() {
+        let deletion_logs = replay_logs(&[(1, "删贴", 1), (1, "恢复删贴", 2), (1, "删贴", 3)]);
+        assert_eq!(deletion_logs, BTreeMap::from([(1, 3)]));
+        let deletion_logs = replay_logs(&[(1, "删贴", 1), (1, "恢复删贴", 2)]);
+        assert!(deletion_logs.is_empty());
+    }
+
+    #[test]
+    fn repeated_deletion_keeps_the_first_log() {
+        let deletion_logs = replay_logs(&[(1, "删贴", 1), (1, "删贴", 2)]);
+        assert_eq!(deletion_logs, BTreeMap::from([(1, 1)]));
+        // a restore of something never deleted changes nothing
+        let deletion_logs = replay_logs(&[(1, "恢复删贴", 1), (1, "删贴", 2)]);
+        assert_eq!(deletion_logs, BTreeMap::from([(1, 2)]));
+    }
+
+    #[test]
+    fn same_operation_time_follows_the_input_order() {
+        // both rows share one operation_time, the caller's ORDER BY decides
+        let deletion_logs = replay_logs(&[(1, "删贴", 1), (1, "恢复删贴", 2)]);
+        assert!(deletion_logs.is_empty());
+        let deletion_logs = replay_logs(&[(1, "恢复删贴", 2), (1, "删贴", 1)]);
+        assert_eq!(deletion_logs, BTreeMap::from([(1, 1)]));
+    }
+
+    #[test]
+    fn other_operations_leave_the_state_alone() {
+        let deletion_logs = replay_logs(&[
+            (1, "加精", 1),
+            (1, "置顶", 2),
+            (2, "删贴", 3),
+            (2, "取消置顶", 4),
+        ]);
+        assert_eq!(deletion_logs, BTreeMap::from([(2, 3)]));
+    }
+
+    #[test]
+    fn keys_are_replayed_independently() {
+        // a thread-level log is keyed by thread_id, a post-level one by post_id
+        let deletion_logs = replay_logs(&[
+            (100, "删贴", 1),     // thread 100
+            (101, "删贴", 2),     // post 101 in thread 100
+            (100, "恢复删贴", 3), // thread 100 restored, post 101 stays deleted
+        ]);
+        assert_eq!(deletion_logs, BTreeMap::from([(101, 2)]));
+    }
+}